@@ -0,0 +1,89 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::game::{CellType, Puzzle};
+
+const WARMUP_SECONDS: f64 = 1.;
+const MEASURE_SECONDS: f64 = 10.;
+const EDITS_PER_FRAME: usize = 20;
+const DEFAULT_SIZE: usize = 200;
+
+// Frame times recorded while `--benchmark <size>` makes random edits to a
+// generated board every frame
+pub struct Benchmark {
+    pub size: usize,
+    frame_times: Vec<f32>,
+    rng: StdRng,
+    finished: bool,
+}
+
+impl Benchmark {
+    // Reads `--benchmark [size]` from the command line
+    pub fn from_args() -> Option<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let flag = args.iter().position(|arg| arg == "--benchmark")?;
+        let size = args
+            .get(flag + 1)
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(DEFAULT_SIZE);
+        Some(Self {
+            size,
+            frame_times: Vec::new(),
+            rng: StdRng::seed_from_u64(0),
+            finished: false,
+        })
+    }
+
+    fn report(&mut self) {
+        self.frame_times.sort_by(f32::total_cmp);
+        let count = self.frame_times.len().max(1);
+        let mean = self.frame_times.iter().sum::<f32>() / count as f32;
+        let percentile = |p: f32| {
+            let index = ((count - 1) as f32 * p) as usize;
+            self.frame_times.get(index).copied().unwrap_or_default()
+        };
+        println!(
+            "{0}x{0} board, {1} edits per frame, {2} frames: \
+             mean {3:.2} ms, median {4:.2} ms, p99 {5:.2} ms, max {6:.2} ms",
+            self.size,
+            EDITS_PER_FRAME,
+            self.frame_times.len(),
+            mean * 1000.,
+            percentile(0.5) * 1000.,
+            percentile(0.99) * 1000.,
+            percentile(1.) * 1000.,
+        );
+    }
+}
+
+pub fn run_benchmark(
+    time: Res<Time>,
+    mut benchmark: ResMut<Benchmark>,
+    mut puzzle: ResMut<Puzzle>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let elapsed = time.seconds_since_startup();
+    if elapsed < WARMUP_SECONDS || benchmark.finished {
+        return;
+    }
+    if elapsed > WARMUP_SECONDS + MEASURE_SECONDS {
+        benchmark.report();
+        benchmark.finished = true;
+        exit.send(AppExit);
+        return;
+    }
+
+    benchmark.frame_times.push(time.delta_seconds());
+    let size = benchmark.size;
+    for _ in 0..EDITS_PER_FRAME {
+        let row = benchmark.rng.gen_range(0..size);
+        let col = benchmark.rng.gen_range(0..size);
+        let cell_type = if benchmark.rng.gen_bool(0.5) {
+            CellType::Filled
+        } else {
+            CellType::Cross
+        };
+        puzzle.board.toggle_mark(row, col, cell_type);
+    }
+}
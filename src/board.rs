@@ -0,0 +1,174 @@
+const WORD_BITS: usize = u64::BITS as usize;
+
+// A square board of filled/empty cells, packed one bit per cell. Every row
+// and every column is stored as its own run of words, so both can be read a
+// word at a time. Bits past the end of a line are always clear.
+#[derive(Clone, Debug, Eq)]
+pub struct BitBoard {
+    size: usize,
+    words_per_line: usize,
+    rows: Vec<u64>,
+    cols: Vec<u64>,
+}
+
+impl PartialEq for BitBoard {
+    // The columns are a copy of the rows, so only the rows need comparing
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.rows == other.rows
+    }
+}
+
+impl BitBoard {
+    pub fn new(size: usize) -> Self {
+        let words_per_line = size.div_ceil(WORD_BITS);
+        Self {
+            size,
+            words_per_line,
+            rows: vec![0; size * words_per_line],
+            cols: vec![0; size * words_per_line],
+        }
+    }
+
+    pub fn from_fn(size: usize, mut filled: impl FnMut(usize, usize) -> bool) -> Self {
+        let mut board = Self::new(size);
+        for row in 0..size {
+            for col in 0..size {
+                if filled(row, col) {
+                    board.set(row, col, true);
+                }
+            }
+        }
+        board
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        self.row(row).get(col)
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, filled: bool) {
+        set_bit(&mut self.rows[row * self.words_per_line..], col, filled);
+        set_bit(&mut self.cols[col * self.words_per_line..], row, filled);
+    }
+
+    pub fn row(&self, row: usize) -> Line<'_> {
+        let start = row * self.words_per_line;
+        Line {
+            words: &self.rows[start..start + self.words_per_line],
+            len: self.size,
+        }
+    }
+
+    pub fn col(&self, col: usize) -> Line<'_> {
+        let start = col * self.words_per_line;
+        Line {
+            words: &self.cols[start..start + self.words_per_line],
+            len: self.size,
+        }
+    }
+}
+
+fn set_bit(words: &mut [u64], i: usize, value: bool) {
+    let mask = 1 << (i % WORD_BITS);
+    if value {
+        words[i / WORD_BITS] |= mask;
+    } else {
+        words[i / WORD_BITS] &= !mask;
+    }
+}
+
+// A borrowed row or column of a board
+#[derive(Copy, Clone, Debug)]
+pub struct Line<'a> {
+    words: &'a [u64],
+    len: usize,
+}
+
+impl<'a> Line<'a> {
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "cell {} is outside a line of {}", i, self.len);
+        self.words[i / WORD_BITS] & (1 << (i % WORD_BITS)) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + 'a {
+        let line = *self;
+        (0..self.len).map(move |i| line.get(i))
+    }
+
+    // The lengths of the runs of filled cells, in order, without allocating
+    pub fn runs(&self) -> Runs<'a> {
+        Runs {
+            line: *self,
+            pos: 0,
+        }
+    }
+
+    // The first cell at or after `from` that is filled (or empty, if
+    // `filled` is false), a word at a time
+    fn next(&self, from: usize, filled: bool) -> Option<usize> {
+        let mut index = from / WORD_BITS;
+        let mut mask = !0u64 << (from % WORD_BITS);
+        while index < self.words.len() {
+            let word = if filled {
+                self.words[index]
+            } else {
+                !self.words[index]
+            };
+            let found = word & mask;
+            if found != 0 {
+                let i = index * WORD_BITS + found.trailing_zeros() as usize;
+                return (i < self.len).then_some(i);
+            }
+            index += 1;
+            mask = !0;
+        }
+        None
+    }
+}
+
+pub struct Runs<'a> {
+    line: Line<'a>,
+    pos: usize,
+}
+
+impl Iterator for Runs<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let start = self.line.next(self.pos, true)?;
+        let end = self.line.next(start, false).unwrap_or(self.line.len);
+        self.pos = end;
+        Some(end - start)
+    }
+}
+
+// The clues for a line. An empty line has the single clue 0.
+pub fn count_runs(line: Line) -> Vec<usize> {
+    let mut runs: Vec<usize> = line.runs().collect();
+
+    if runs.is_empty() {
+        runs.push(0);
+    }
+
+    runs
+}
+
+// The row and column clues of a board
+pub fn clues(board: &BitBoard) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+    let size = board.size();
+    let row_runs = (0..size).map(|i| count_runs(board.row(i))).collect();
+    let col_runs = (0..size).map(|i| count_runs(board.col(i))).collect();
+    (row_runs, col_runs)
+}
+
+// Whether a line's runs are exactly its clues, without allocating
+pub fn line_matches(line: Line, clues: &[usize]) -> bool {
+    let clues = match clues {
+        [0] => &[],
+        clues => clues,
+    };
+    line.runs().eq(clues.iter().copied())
+}
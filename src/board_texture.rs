@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    Extent3d, FilterMode, SamplerDescriptor, TextureDimension, TextureFormat,
+};
+
+use crate::game::{CellType, Mark};
+use crate::theme::Theme;
+
+// Boards at least this big are drawn into one texture instead of one sprite
+// per cell and gridline
+pub const TEXTURE_BOARD_SIZE: usize = 40;
+
+// Upper bound on the texture's side, in pixels
+const MAX_TEXTURE_SIDE: usize = 2048;
+const MIN_CELL_PIXELS: usize = 3;
+const MAX_CELL_PIXELS: usize = 16;
+
+const BYTES_PER_PIXEL: usize = 4;
+
+fn color_bytes(color: Color) -> [u8; 4] {
+    color.as_rgba_f32().map(|c| (c * 255.).round() as u8)
+}
+
+// A board drawn into an RGBA image, one square of pixels per cell. Gridlines
+// are drawn along the top and left edges of each cell, thicker every fifth line.
+#[derive(Copy, Clone)]
+pub struct BoardTexture {
+    pub size: usize,
+    pub cell_pixels: usize,
+}
+
+impl BoardTexture {
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            cell_pixels: (MAX_TEXTURE_SIDE / size).clamp(MIN_CELL_PIXELS, MAX_CELL_PIXELS),
+        }
+    }
+
+    fn side(&self) -> usize {
+        self.size * self.cell_pixels
+    }
+
+    pub fn create_image(&self, theme: &Theme) -> Image {
+        let side = self.side() as u32;
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: side,
+                height: side,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &color_bytes(theme.grid_background),
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        // Keep cell edges sharp when zoomed in
+        image.sampler_descriptor = SamplerDescriptor {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        };
+
+        let line = color_bytes(theme.gridline);
+        for i in 1..self.size {
+            let width = self.line_width(i);
+            for offset in 0..width {
+                let at = i * self.cell_pixels + offset;
+                for along in 0..self.side() {
+                    self.put(&mut image.data, at, along, line);
+                    self.put(&mut image.data, along, at, line);
+                }
+            }
+        }
+        image
+    }
+
+    // Width of the gridline before cell `i`
+    fn line_width(&self, i: usize) -> usize {
+        if i == 0 {
+            0
+        } else if i.is_multiple_of(5) && self.cell_pixels >= 6 {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn put(&self, data: &mut [u8], x: usize, y: usize, color: [u8; 4]) {
+        let start = (y * self.side() + x) * BYTES_PER_PIXEL;
+        data[start..start + BYTES_PER_PIXEL].copy_from_slice(&color);
+    }
+
+    // Redraws the inside of one cell, leaving its gridlines alone. Mistakes
+    // are drawn in the theme's mistake color.
+    pub fn draw_cell(
+        &self,
+        data: &mut [u8],
+        theme: &Theme,
+        row: usize,
+        col: usize,
+        mark: Option<Mark>,
+        mistake: bool,
+    ) {
+        let left = col * self.cell_pixels + self.line_width(col);
+        let top = row * self.cell_pixels + self.line_width(row);
+        let width = (col + 1) * self.cell_pixels - left;
+        let height = (row + 1) * self.cell_pixels - top;
+
+        let background = color_bytes(theme.grid_background);
+        let color = mark.map(|mark| color_bytes(theme.cell_color(mark, mistake)));
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = match (mark, color) {
+                    (Some(mark), Some(color))
+                        if inked(mark.cell_type, mistake, x, y, width, height) =>
+                    {
+                        color
+                    }
+                    _ => background,
+                };
+                self.put(data, left + x, top + y, pixel);
+            }
+        }
+    }
+}
+
+// Side of the images cell sprites are drawn with
+const MARK_IMAGE_PIXELS: usize = 32;
+
+// A mark drawn in white on transparent, for cell sprites to tint
+pub fn mark_image(cell_type: CellType, mistake: bool) -> Image {
+    let side = MARK_IMAGE_PIXELS;
+    let mut data = Vec::with_capacity(side * side * BYTES_PER_PIXEL);
+    for y in 0..side {
+        for x in 0..side {
+            let alpha = if inked(cell_type, mistake, x, y, side, side) {
+                255
+            } else {
+                0
+            };
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: side as u32,
+            height: side as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+// Whether pixel (x, y) of a cell is part of a mark. Mistakes are framed,
+// and mistaken fills hatched, so that they stand out by shape as well as
+// color.
+fn inked(
+    cell_type: CellType,
+    mistake: bool,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> bool {
+    let stroke = width / 8;
+    let frame = (width / 10).max(1);
+    let framed = x < frame || y < frame || x + frame >= width || y + frame >= height;
+    match cell_type {
+        CellType::Filled if mistake => framed || ((x + y) / (width / 6).max(1)).is_multiple_of(2),
+        CellType::Filled => true,
+        CellType::Dot => {
+            let dot = (width.min(height) * 3 / 10).max(1);
+            x.abs_diff(width / 2) * 2 < dot && y.abs_diff(height / 2) * 2 < dot
+        }
+        // Both diagonals; cells are square up to a gridline's width
+        CellType::Cross => {
+            (mistake && framed) || x.abs_diff(y) <= stroke || (x + y).abs_diff(width - 1) <= stroke
+        }
+    }
+}
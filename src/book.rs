@@ -0,0 +1,474 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{clues, BitBoard};
+use crate::export::{self, ExportBoard, Geometry, DIGIT_HEIGHT};
+use crate::game::CellType;
+use crate::layout::MONO_CHAR_WIDTH;
+use crate::solver::Solver;
+
+// Everything is measured in points, 72 to the inch, with the origin at the
+// top left of the page
+const PAGE_MARGIN: f32 = 36.;
+// Space between the puzzles on a page
+const SLOT_GAP: f32 = 18.;
+const HEADER_SIZE: f32 = 14.;
+const FOOTER_SIZE: f32 = 9.;
+const TITLE_SIZE: f32 = 10.;
+// Space below each band of text
+const TEXT_GAP: f32 = 8.;
+
+// Small puzzles are never drawn bigger than this, however much room they have
+const MAX_CELL: f32 = 18.;
+// Puzzles need cells big enough to pencil in; solutions only need to be read
+const MIN_PUZZLE_CELL: f32 = 10.;
+const MIN_SOLUTION_CELL: f32 = 4.;
+
+// Columns and rows of puzzles per page, from most to fewest. Each page uses
+// the first that fits the puzzles that come next.
+const LAYOUTS: [(usize, usize); 6] = [(4, 5), (3, 4), (2, 3), (2, 2), (1, 2), (1, 1)];
+
+// The built-in fonts every PDF reader has, so nothing needs embedding.
+// Courier advances 0.6em per character, like the game's Fira Mono.
+const CLUE_FONT: &str = "Courier";
+const TEXT_FONT: &str = "Courier-Bold";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Paper {
+    A4,
+    Letter,
+}
+
+impl Paper {
+    // Width and height in points
+    pub fn size(&self) -> Vec2 {
+        match self {
+            Paper::A4 => Vec2::new(595.28, 841.89),
+            Paper::Letter => Vec2::new(612., 792.),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BookOptions {
+    pub paper: Paper,
+    // Whether the solutions follow the puzzles
+    pub solutions: bool,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        Self {
+            paper: Paper::A4,
+            solutions: true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    // Boards that line by line logic solves are easy when small and medium
+    // otherwise; boards that need a guess are hard
+    pub fn rate(solution: &BitBoard) -> Self {
+        let (row_runs, col_runs) = clues(solution);
+        let (_, guesses) = Solver::new(&row_runs, &col_runs).solve_counting_guesses();
+        if guesses > 0 {
+            Difficulty::Hard
+        } else if solution.size() <= 10 {
+            Difficulty::Easy
+        } else {
+            Difficulty::Medium
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+}
+
+// One of the book's puzzles, numbered from 1
+struct Entry {
+    title: String,
+    blank: ExportBoard,
+    solved: ExportBoard,
+}
+
+impl Entry {
+    fn new(number: usize, solution: &BitBoard) -> Self {
+        let size = solution.size();
+        Self {
+            title: format!(
+                "{}. {} {}x{}",
+                number,
+                Difficulty::rate(solution).name(),
+                size,
+                size
+            ),
+            blank: ExportBoard::blank(solution),
+            solved: ExportBoard::solved(solution),
+        }
+    }
+}
+
+// Where the puzzles go on each page of a section
+struct PageLayout {
+    paper: Vec2,
+    columns: usize,
+    rows: usize,
+}
+
+impl PageLayout {
+    // The most puzzles per page that still leaves each of the boards that
+    // would go on it at least `min_cell`
+    fn choose(paper: Paper, boards: &[(&str, &ExportBoard)], min_cell: f32) -> Self {
+        let fits = |layout: &PageLayout| {
+            boards
+                .iter()
+                .take(layout.per_page())
+                .all(|(_, board)| layout.cell_size(board) >= min_cell)
+        };
+        LAYOUTS
+            .iter()
+            .map(|&(columns, rows)| PageLayout {
+                paper: paper.size(),
+                columns,
+                rows,
+            })
+            .find(fits)
+            .unwrap_or(PageLayout {
+                paper: paper.size(),
+                columns: 1,
+                rows: 1,
+            })
+    }
+
+    fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    // Top left corner and size of each puzzle's share of the page
+    fn slot(&self, index: usize) -> (Vec2, Vec2) {
+        let top = PAGE_MARGIN + HEADER_SIZE + TEXT_GAP;
+        let bottom = PAGE_MARGIN + FOOTER_SIZE + TEXT_GAP;
+        let area = Vec2::new(self.paper.x - 2. * PAGE_MARGIN, self.paper.y - top - bottom);
+        let gaps = Vec2::new(self.columns as f32 - 1., self.rows as f32 - 1.) * SLOT_GAP;
+        let size = (area - gaps) / Vec2::new(self.columns as f32, self.rows as f32);
+        let (column, row) = (index % self.columns, index / self.columns);
+        let corner = Vec2::new(
+            PAGE_MARGIN + column as f32 * (size.x + SLOT_GAP),
+            top + row as f32 * (size.y + SLOT_GAP),
+        );
+        (corner, size)
+    }
+
+    // The biggest cells that fit a board and its clues into a slot, below its
+    // title
+    fn cell_size(&self, board: &ExportBoard) -> f32 {
+        let (_, slot) = self.slot(0);
+        let room = slot - Vec2::new(0., TITLE_SIZE + TEXT_GAP);
+        let fit = room / Geometry::extent(board);
+        fit.x.min(fit.y).min(MAX_CELL)
+    }
+}
+
+// PDF numbers, without needless digits
+fn num(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+// A page's drawing operators, in coordinates with y pointing down
+struct Page {
+    content: String,
+}
+
+impl Page {
+    fn new(paper: Vec2) -> Self {
+        let mut content = String::new();
+        // Writing to a string can't fail
+        let _ = writeln!(content, "1 0 0 -1 0 {} cm", num(paper.y));
+        Self { content }
+    }
+
+    // Text centred on `center.x`, with its digits centred on `center.y`.
+    // Text is flipped back upright, since the page's y axis is.
+    fn text(&mut self, font: &str, size: f32, center: Vec2, text: &str) {
+        let width = text.len() as f32 * MONO_CHAR_WIDTH * size;
+        let x = center.x - width / 2.;
+        let baseline = center.y + size * DIGIT_HEIGHT / 2.;
+        let escaped = text
+            .replace('\\', "\\\\")
+            .replace('(', "\\(")
+            .replace(')', "\\)");
+        let _ = writeln!(
+            self.content,
+            "BT /{} {} Tf 1 0 0 -1 {} {} Tm ({}) Tj ET",
+            font_resource(font),
+            num(size),
+            num(x),
+            num(baseline),
+            escaped
+        );
+    }
+
+    fn rect(&mut self, corner: Vec2, size: Vec2) {
+        let _ = writeln!(
+            self.content,
+            "{} {} {} {} re f",
+            num(corner.x),
+            num(corner.y),
+            num(size.x),
+            num(size.y)
+        );
+    }
+
+    // Draws a board with its top left corner at `corner`, laid out as in
+    // `export`. Crosses are left out, so solutions read as just the picture.
+    fn board(&mut self, board: &ExportBoard, corner: Vec2, cell: f32) {
+        let geometry = Geometry::scaled(board, cell, 0.);
+        let size = board.size();
+        let origin = corner + geometry.grid_origin;
+        for row in 0..size {
+            for col in 0..size {
+                if board.mark(row, col) == Some(CellType::Filled) {
+                    self.rect(corner + geometry.cell_corner(row, col), Vec2::splat(cell));
+                }
+            }
+        }
+
+        let grid_side = size as f32 * cell;
+        for i in 0..=size {
+            let thickness = geometry.line_thickness(i, size);
+            let start = origin - Vec2::splat(thickness / 2.);
+            let offset = i as f32 * cell;
+            self.rect(
+                start + Vec2::new(offset, 0.),
+                Vec2::new(thickness, grid_side + thickness),
+            );
+            self.rect(
+                start + Vec2::new(0., offset),
+                Vec2::new(grid_side + thickness, thickness),
+            );
+        }
+
+        for (text, center) in geometry.clue_labels(board) {
+            self.text(CLUE_FONT, geometry.font_size, corner + center, &text);
+        }
+    }
+}
+
+fn font_resource(font: &str) -> &'static str {
+    if font == CLUE_FONT {
+        "F1"
+    } else {
+        "F2"
+    }
+}
+
+// Lays out one section of the book, such as the puzzles or their solutions,
+// starting a new page
+fn add_section(
+    pages: &mut Vec<Page>,
+    heading: &str,
+    mut boards: &[(&str, &ExportBoard)],
+    paper: Paper,
+    min_cell: f32,
+) {
+    while !boards.is_empty() {
+        let layout = PageLayout::choose(paper, boards, min_cell);
+        let (chunk, rest) = boards.split_at(layout.per_page().min(boards.len()));
+        boards = rest;
+        let paper = layout.paper;
+        let mut page = Page::new(paper);
+        page.text(
+            TEXT_FONT,
+            HEADER_SIZE,
+            Vec2::new(paper.x / 2., PAGE_MARGIN + HEADER_SIZE / 2.),
+            heading,
+        );
+        for (index, (title, board)) in chunk.iter().enumerate() {
+            let (corner, slot) = layout.slot(index);
+            let cell = layout.cell_size(board);
+            let width = Geometry::extent(board).x * cell;
+            page.text(
+                TEXT_FONT,
+                TITLE_SIZE,
+                corner + Vec2::new(slot.x / 2., TITLE_SIZE / 2.),
+                title,
+            );
+            let board_corner = corner + Vec2::new((slot.x - width) / 2., TITLE_SIZE + TEXT_GAP);
+            page.board(board, board_corner, cell);
+        }
+        pages.push(page);
+    }
+}
+
+// The puzzles, numbered and rated, and then their solutions, as a PDF
+pub fn to_pdf(solutions: &[BitBoard], options: &BookOptions) -> Vec<u8> {
+    let entries: Vec<Entry> = solutions
+        .iter()
+        .enumerate()
+        .map(|(index, solution)| Entry::new(index + 1, solution))
+        .collect();
+    let mut pages = Vec::new();
+    let puzzles: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.title.as_str(), &entry.blank))
+        .collect();
+    add_section(
+        &mut pages,
+        "Puzzles",
+        &puzzles,
+        options.paper,
+        MIN_PUZZLE_CELL,
+    );
+    if options.solutions {
+        let solved: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.title.as_str(), &entry.solved))
+            .collect();
+        add_section(
+            &mut pages,
+            "Solutions",
+            &solved,
+            options.paper,
+            MIN_SOLUTION_CELL,
+        );
+    }
+
+    let paper = options.paper.size();
+    if pages.is_empty() {
+        pages.push(Page::new(paper));
+    }
+    let count = pages.len();
+    for (index, page) in pages.iter_mut().enumerate() {
+        let footer = format!("{} / {}", index + 1, count);
+        let y = paper.y - PAGE_MARGIN - FOOTER_SIZE / 2.;
+        page.text(TEXT_FONT, FOOTER_SIZE, Vec2::new(paper.x / 2., y), &footer);
+    }
+    write_document(&pages, paper)
+}
+
+// Objects 1 to 4 are the catalog, the page tree and the two fonts; each page
+// is then a page object followed by its content stream
+fn write_document(pages: &[Page], paper: Vec2) -> Vec<u8> {
+    let page_object = |index: usize| 5 + 2 * index;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", page_object(index)))
+        .collect();
+    let font = |name: &str| {
+        format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            name
+        )
+    };
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+        font(CLUE_FONT),
+        font(TEXT_FONT),
+    ];
+    for (index, page) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            num(paper.x),
+            num(paper.y),
+            page_object(index) + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            page.content.len(),
+            page.content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = writeln!(pdf, "{} 0 obj\n{}\nendobj", index + 1, object);
+    }
+    let xref = pdf.len();
+    let _ = writeln!(pdf, "xref\n0 {}", objects.len() + 1);
+    // Each entry is exactly 20 bytes, counting its two-character line end
+    pdf.push_str("0000000000 65535 f \n");
+    for offset in offsets {
+        let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    pdf.into_bytes()
+}
+
+pub fn write(solutions: &[BitBoard], options: &BookOptions, path: &Path) -> io::Result<()> {
+    fs::write(path, to_pdf(solutions, options))
+}
+
+pub struct BookArgs {
+    pub path: PathBuf,
+    pub options: BookOptions,
+}
+
+// Reads `--book <file> [--paper a4|letter] [--no-solutions]` from the command
+// line
+pub fn options_from_args() -> Option<BookArgs> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--book")?;
+    let value = |name: &str| {
+        let at = args.iter().position(|arg| arg == name)?;
+        args.get(at + 1)
+    };
+    Some(BookArgs {
+        path: args
+            .get(flag + 1)
+            .map_or_else(|| "puzzles.pdf".into(), PathBuf::from),
+        options: BookOptions {
+            paper: match value("--paper").map(String::as_str) {
+                Some("letter") => Paper::Letter,
+                _ => Paper::A4,
+            },
+            solutions: !args.iter().any(|arg| arg == "--no-solutions"),
+        },
+    })
+}
+
+// Makes a book of every puzzle code read from stdin, numbered in the order
+// they come
+pub fn run(args: &BookArgs) {
+    let solutions: Vec<BitBoard> = export::codes_from_stdin()
+        .map(|(_, code)| code.solution())
+        .collect();
+    match write(&solutions, &args.options, &args.path) {
+        Ok(()) => eprintln!(
+            "Wrote {} puzzles to {}",
+            solutions.len(),
+            args.path.display()
+        ),
+        Err(err) => eprintln!("Couldn't write {}: {}", args.path.display(), err),
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use crate::MainCamera;
+
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 2.0;
+const ZOOM_STEP: f32 = 1.1;
+// Pixels of smooth scrolling that count as one wheel notch
+const PIXELS_PER_LINE: f32 = 40.;
+// World units per second at zoom 1
+const PAN_SPEED: f32 = 400.;
+
+// Projects a point in window coordinates into the world, through any camera
+// transform and projection
+pub fn window_to_world(
+    win: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window_pos: Vec2,
+) -> Vec2 {
+    let window_size = Vec2::new(win.width(), win.height());
+    let ndc = (window_pos / window_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+    ndc_to_world.project_point3(ndc.extend(-1.0)).truncate()
+}
+
+// The world-space rectangle currently visible through the camera, as (min, max)
+pub fn visible_world_rect(
+    win: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> (Vec2, Vec2) {
+    let a = window_to_world(win, camera, camera_transform, Vec2::ZERO);
+    let b = window_to_world(
+        win,
+        camera,
+        camera_transform,
+        Vec2::new(win.width(), win.height()),
+    );
+    (a.min(b), a.max(b))
+}
+
+pub fn reset_camera(
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let (mut transform, mut projection) = query.single_mut();
+    transform.translation.x = 0.;
+    transform.translation.y = 0.;
+    projection.scale = 1.;
+}
+
+pub fn handle_view_reset(
+    keyboard_input: Res<Input<KeyCode>>,
+    query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Home) {
+        reset_camera(query);
+    }
+}
+
+// Zooms with the mouse wheel around the cursor, or with +/- around the centre
+pub fn zoom_camera(
+    mut wheel_events: EventReader<MouseWheel>,
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut query: Query<
+        (
+            &Camera,
+            &GlobalTransform,
+            &mut Transform,
+            &mut OrthographicProjection,
+        ),
+        With<MainCamera>,
+    >,
+) {
+    let mut notches = 0.;
+    for event in wheel_events.iter() {
+        notches += match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        };
+    }
+    if keyboard_input.just_pressed(KeyCode::Equals) {
+        notches += 1.;
+    }
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        notches -= 1.;
+    }
+    if notches == 0. {
+        return;
+    }
+
+    let win = windows.get_primary().expect("no primary window");
+    let (camera, global_transform, mut transform, mut projection) = query.single_mut();
+
+    let old_scale = projection.scale;
+    let new_scale = (old_scale * ZOOM_STEP.powf(-notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+    if new_scale == old_scale {
+        return;
+    }
+
+    // Keep the world point under the cursor fixed while zooming
+    if let Some(cursor_pos) = win.cursor_position() {
+        let focus = window_to_world(win, camera, global_transform, cursor_pos);
+        let camera_pos = transform.translation.truncate();
+        let new_pos = focus + (camera_pos - focus) * (new_scale / old_scale);
+        transform.translation.x = new_pos.x;
+        transform.translation.y = new_pos.y;
+    }
+    projection.scale = new_scale;
+}
+
+// Pans by dragging with the middle mouse button or holding the arrow keys
+pub fn pan_camera(
+    time: Res<Time>,
+    mouse_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut motion_events: EventReader<MouseMotion>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    let (mut transform, projection) = query.single_mut();
+
+    let mut delta = Vec2::ZERO;
+    if mouse_input.pressed(MouseButton::Middle) {
+        for event in motion_events.iter() {
+            // Window motion is y-down, the world is y-up
+            delta += Vec2::new(-event.delta.x, event.delta.y) * projection.scale;
+        }
+    } else {
+        motion_events.iter().for_each(drop);
+    }
+
+    let mut direction = Vec2::ZERO;
+    if keyboard_input.pressed(KeyCode::Left) {
+        direction.x -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Right) {
+        direction.x += 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Down) {
+        direction.y -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Up) {
+        direction.y += 1.;
+    }
+    delta += direction * PAN_SPEED * projection.scale * time.delta_seconds();
+
+    if delta != Vec2::ZERO {
+        transform.translation += delta.extend(0.);
+    }
+}
@@ -0,0 +1,21 @@
+// Keeps one clipboard alive for the whole session, since on some platforms
+// copied text disappears when the clipboard that set it is dropped
+pub struct Clipboard(Option<arboard::Clipboard>);
+
+impl Clipboard {
+    pub fn get_text(&mut self) -> Option<String> {
+        self.0.as_mut()?.get_text().ok()
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        if let Some(clipboard) = self.0.as_mut() {
+            let _ = clipboard.set_text(text);
+        }
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self(arboard::Clipboard::new().ok())
+    }
+}
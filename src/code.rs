@@ -0,0 +1,132 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::board::BitBoard;
+
+pub const MAX_PUZZLE_SIZE: usize = 255;
+
+// A short, shareable description of a puzzle. Seeded puzzles are written
+// as `<size>-<seed in hex>` (e.g. `10-5eed`), and custom puzzles as `c-`
+// followed by the URL-safe base64 of the size and the solution's bits
+#[derive(Clone, Debug, PartialEq)]
+pub enum PuzzleCode {
+    Seeded { size: usize, seed: u64 },
+    Custom(BitBoard),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseCodeError {
+    Empty,
+    InvalidSize,
+    InvalidSeed,
+    InvalidEncoding,
+    WrongLength,
+}
+
+impl fmt::Display for ParseCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ParseCodeError::Empty => "no code entered",
+            ParseCodeError::InvalidSize => "puzzle size must be between 1 and 255",
+            ParseCodeError::InvalidSeed => "seed must be a hexadecimal number",
+            ParseCodeError::InvalidEncoding => "custom puzzle is not valid base64",
+            ParseCodeError::WrongLength => "custom puzzle data has the wrong length",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for ParseCodeError {}
+
+impl PuzzleCode {
+    pub fn random(size: usize) -> Self {
+        Self::Seeded {
+            size,
+            seed: thread_rng().gen(),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            PuzzleCode::Seeded { size, .. } => *size,
+            PuzzleCode::Custom(solution) => solution.size(),
+        }
+    }
+
+    // Builds the solution this code describes; always the same for a given
+    // code. Seeded boards use ChaCha12 by name rather than `StdRng`, whose
+    // algorithm rand may change, so that shared codes, packs and replays
+    // keep their puzzles.
+    pub fn solution(&self) -> BitBoard {
+        match self {
+            PuzzleCode::Seeded { size, seed } => {
+                let mut rng = ChaCha12Rng::seed_from_u64(*seed);
+                BitBoard::from_fn(*size, |_, _| rng.gen_bool(0.5))
+            }
+            PuzzleCode::Custom(solution) => solution.clone(),
+        }
+    }
+}
+
+impl fmt::Display for PuzzleCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleCode::Seeded { size, seed } => write!(f, "{}-{:x}", size, seed),
+            PuzzleCode::Custom(solution) => {
+                let size = solution.size();
+                let mut bytes = vec![size as u8];
+                bytes.resize(1 + (size * size).div_ceil(8), 0);
+                let cells = (0..size).flat_map(|row| solution.row(row).iter());
+                for (i, filled) in cells.enumerate() {
+                    if filled {
+                        bytes[1 + i / 8] |= 0x80 >> (i % 8);
+                    }
+                }
+                write!(
+                    f,
+                    "c-{}",
+                    base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for PuzzleCode {
+    type Err = ParseCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (prefix, body) = s.split_once('-').ok_or(if s.is_empty() {
+            ParseCodeError::Empty
+        } else {
+            ParseCodeError::InvalidSize
+        })?;
+
+        if prefix.eq_ignore_ascii_case("c") {
+            let bytes = base64::decode_config(body, base64::URL_SAFE_NO_PAD)
+                .map_err(|_| ParseCodeError::InvalidEncoding)?;
+            let size = *bytes.first().ok_or(ParseCodeError::WrongLength)? as usize;
+            if size == 0 {
+                return Err(ParseCodeError::InvalidSize);
+            }
+            if bytes.len() != 1 + (size * size).div_ceil(8) {
+                return Err(ParseCodeError::WrongLength);
+            }
+            let bit = |i: usize| bytes[1 + i / 8] & (0x80 >> (i % 8)) != 0;
+            let solution = BitBoard::from_fn(size, |row, col| bit(row * size + col));
+            return Ok(PuzzleCode::Custom(solution));
+        }
+
+        let size = prefix
+            .parse::<usize>()
+            .ok()
+            .filter(|size| (1..=MAX_PUZZLE_SIZE).contains(size))
+            .ok_or(ParseCodeError::InvalidSize)?;
+        let seed = u64::from_str_radix(body, 16).map_err(|_| ParseCodeError::InvalidSeed)?;
+        Ok(PuzzleCode::Seeded { size, seed })
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::board::BitBoard;
+use crate::code::MAX_PUZZLE_SIZE;
+use crate::model::BoardLine;
+use crate::solver::{Solution, Solver};
+
+// Something wrong with a puzzle's clues, pointing at the line it is in where
+// there is one
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClueProblem {
+    InvalidSize(usize),
+    RowCount {
+        found: usize,
+        size: usize,
+    },
+    ColumnCount {
+        found: usize,
+        size: usize,
+    },
+    // A clue with 0 and other numbers, like `[2, 0]`
+    ZeroInClue(BoardLine),
+    // A clue whose runs and the gaps between them are longer than the line
+    ClueTooLong {
+        line: BoardLine,
+        needs: usize,
+        size: usize,
+    },
+    TotalsDiffer {
+        rows: usize,
+        columns: usize,
+    },
+    // A line that fits its clue on its own, but which the solver found no
+    // way to fill that agrees with the lines crossing it
+    ImpossibleLine(BoardLine),
+    NoSolution,
+    MultipleSolutions,
+    TooHard,
+}
+
+// Lines are numbered from 1 in messages, as players count them
+pub(crate) fn line_name(line: BoardLine) -> String {
+    match line {
+        BoardLine::Row(row) => format!("row {}", row + 1),
+        BoardLine::Col(col) => format!("column {}", col + 1),
+    }
+}
+
+impl fmt::Display for ClueProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClueProblem::InvalidSize(size) => {
+                write!(f, "size {} is not between 1 and {}", size, MAX_PUZZLE_SIZE)
+            }
+            ClueProblem::RowCount { found, size } => {
+                write!(f, "{} row clues for a board {} tall", found, size)
+            }
+            ClueProblem::ColumnCount { found, size } => {
+                write!(f, "{} column clues for a board {} wide", found, size)
+            }
+            ClueProblem::ZeroInClue(line) => {
+                write!(f, "{}'s clue has a 0 among other numbers", line_name(*line))
+            }
+            ClueProblem::ClueTooLong { line, needs, size } => write!(
+                f,
+                "{}'s clue needs {} cells, but lines are {} long",
+                line_name(*line),
+                needs,
+                size
+            ),
+            ClueProblem::TotalsDiffer { rows, columns } => write!(
+                f,
+                "the row clues fill {} cells, but the column clues fill {}",
+                rows, columns
+            ),
+            ClueProblem::ImpossibleLine(line) => write!(
+                f,
+                "{} can't be filled in to agree with the lines crossing it",
+                line_name(*line)
+            ),
+            ClueProblem::NoSolution => write!(f, "the clues have no solution"),
+            ClueProblem::MultipleSolutions => write!(
+                f,
+                "the clues have more than one solution; give the intended one"
+            ),
+            ClueProblem::TooHard => write!(
+                f,
+                "the clues are too hard to check for a single solution; give the solution"
+            ),
+        }
+    }
+}
+
+// Every problem with the shape of some clues: how many there are, whether
+// each fits its line, and whether the rows and columns fill as many cells.
+// Empty lines have the clue `[0]`.
+pub fn check_clues(size: usize, rows: &[Vec<usize>], columns: &[Vec<usize>]) -> Vec<ClueProblem> {
+    if !(1..=MAX_PUZZLE_SIZE).contains(&size) {
+        return vec![ClueProblem::InvalidSize(size)];
+    }
+    let mut problems = Vec::new();
+    if rows.len() != size {
+        let found = rows.len();
+        problems.push(ClueProblem::RowCount { found, size });
+    }
+    if columns.len() != size {
+        let found = columns.len();
+        problems.push(ClueProblem::ColumnCount { found, size });
+    }
+
+    let lines = rows
+        .iter()
+        .enumerate()
+        .map(|(row, clue)| (BoardLine::Row(row), clue))
+        .chain(
+            columns
+                .iter()
+                .enumerate()
+                .map(|(col, clue)| (BoardLine::Col(col), clue)),
+        );
+    for (line, clue) in lines {
+        if clue.len() > 1 && clue.contains(&0) {
+            problems.push(ClueProblem::ZeroInClue(line));
+            continue;
+        }
+        let needs = clue.iter().sum::<usize>() + clue.len().saturating_sub(1);
+        if needs > size {
+            problems.push(ClueProblem::ClueTooLong { line, needs, size });
+        }
+    }
+
+    let total = |clues: &[Vec<usize>]| clues.iter().flatten().sum::<usize>();
+    if total(rows) != total(columns) {
+        problems.push(ClueProblem::TotalsDiffer {
+            rows: total(rows),
+            columns: total(columns),
+        });
+    }
+    problems
+}
+
+// Finds the one board some well-shaped clues describe. Contradictions that
+// line by line logic finds are pinned to the line they were found in.
+pub fn solve_clues(rows: &[Vec<usize>], columns: &[Vec<usize>]) -> Result<BitBoard, ClueProblem> {
+    if let Some(line) = Solver::new(rows, columns).find_contradiction() {
+        return Err(ClueProblem::ImpossibleLine(line));
+    }
+    match Solver::new(rows, columns).solve() {
+        Solution::Unique(solution) => Ok(solution),
+        Solution::None => Err(ClueProblem::NoSolution),
+        Solution::Multiple => Err(ClueProblem::MultipleSolutions),
+        Solution::TooHard => Err(ClueProblem::TooHard),
+    }
+}
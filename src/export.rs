@@ -0,0 +1,666 @@
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+
+use crate::board::{clues, BitBoard};
+use crate::code::PuzzleCode;
+use crate::game::Puzzle;
+use crate::layout::{row_clue_offsets, ClueExtents, CLUE_CELL_RATIO, LINE_HEIGHT, MONO_CHAR_WIDTH};
+use crate::model::{Board, CellType, Layer};
+
+const FONT: &[u8] = include_bytes!("../assets/fonts/FiraMono-Medium.ttf");
+const FONT_FAMILY: &str = "Fira Mono, monospace";
+// Digits stand this fraction of the font size above the baseline, which is
+// used to centre them
+pub(crate) const DIGIT_HEIGHT: f32 = 0.7;
+
+// Gray levels, drawn black on white for printing
+const PAPER: u8 = 0xff;
+const INK: u8 = 0x00;
+const CROSS_INK: u8 = 0x60;
+
+// PNGs are drawn in memory, so they are kept to this many pixels, as many as
+// a 16384 pixel square
+pub const MAX_PNG_PIXELS: usize = 1 << 28;
+
+// Gridlines are this much thicker on the border and after every fifth cell,
+// as on screen
+const THICK_LINE_FACTOR: f32 = 3.;
+
+// How an exported board is drawn, in pixels (or SVG user units)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExportOptions {
+    pub cell_size: u32,
+    // Blank space around the clues and grid
+    pub margin: u32,
+    // Whether crossed cells show their crosses
+    pub crosses: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 24,
+            margin: 16,
+            crosses: true,
+        }
+    }
+}
+
+// A board to export: its clues, and what is drawn in each cell
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportBoard {
+    size: usize,
+    row_runs: Vec<Vec<usize>>,
+    col_runs: Vec<Vec<usize>>,
+    // Row by row; dots are never drawn
+    marks: Vec<Option<CellType>>,
+}
+
+impl ExportBoard {
+    // The unsolved puzzle, for solving on paper
+    pub fn blank(solution: &BitBoard) -> Self {
+        Self::with_marks(solution, |_, _| None)
+    }
+
+    // The solution, with every empty cell crossed out
+    pub fn solved(solution: &BitBoard) -> Self {
+        Self::with_marks(solution, |row, col| {
+            Some(if solution.get(row, col) {
+                CellType::Filled
+            } else {
+                CellType::Cross
+            })
+        })
+    }
+
+    // A game's board as the player has marked it so far, leaving out
+    // tentative marks
+    pub fn from_board(board: &Board) -> Self {
+        Self::with_marks(board.get_solution(), |row, col| {
+            board
+                .mark_at(row, col)
+                .filter(|mark| mark.layer != Layer::Tentative)
+                .map(|mark| mark.cell_type)
+        })
+    }
+
+    fn with_marks(
+        solution: &BitBoard,
+        mut mark: impl FnMut(usize, usize) -> Option<CellType>,
+    ) -> Self {
+        let size = solution.size();
+        let (row_runs, col_runs) = clues(solution);
+        let marks = (0..size)
+            .flat_map(|row| (0..size).map(move |col| (row, col)))
+            .map(|(row, col)| mark(row, col))
+            .collect();
+        Self {
+            size,
+            row_runs,
+            col_runs,
+            marks,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(crate) fn mark(&self, row: usize, col: usize) -> Option<CellType> {
+        self.marks[row * self.size + col]
+    }
+}
+
+// Where everything goes, following the in-game layout: row clues to the left
+// of the grid and column clues above it, in a font sized to the cells
+pub(crate) struct Geometry {
+    cell: f32,
+    pub font_size: f32,
+    pub grid_origin: Vec2,
+    width: u32,
+    height: u32,
+    thin_line: f32,
+}
+
+impl Geometry {
+    fn new(board: &ExportBoard, options: &ExportOptions) -> Self {
+        let mut geometry = Self::scaled(
+            board,
+            options.cell_size.max(1) as f32,
+            options.margin as f32,
+        );
+        // On whole pixels, so gridlines are sharp
+        geometry.grid_origin = geometry.grid_origin.round();
+        geometry.thin_line = geometry.thin_line.max(1.);
+        geometry
+    }
+
+    // The layout at any scale, such as in points for printing
+    pub fn scaled(board: &ExportBoard, cell: f32, margin: f32) -> Self {
+        let clue_space = Self::clue_space(board) * cell;
+        let grid_side = board.size as f32 * cell;
+        Self {
+            cell,
+            font_size: cell * CLUE_CELL_RATIO,
+            grid_origin: Vec2::splat(margin) + clue_space,
+            width: (2. * margin + clue_space.x + grid_side).ceil() as u32,
+            height: (2. * margin + clue_space.y + grid_side).ceil() as u32,
+            thin_line: cell / 24.,
+        }
+    }
+
+    // Room taken by the row clues to the left and the column clues above, in
+    // cells
+    fn clue_space(board: &ExportBoard) -> Vec2 {
+        let clues = ClueExtents::measure(&board.row_runs, &board.col_runs);
+        let row_clue_width = (clues.row_chars as f32 + 1.) * MONO_CHAR_WIDTH;
+        let col_clue_height = clues.col_lines as f32 * LINE_HEIGHT + MONO_CHAR_WIDTH;
+        Vec2::new(row_clue_width, col_clue_height) * CLUE_CELL_RATIO
+    }
+
+    // Width and height of the clues and grid together, in cells
+    pub fn extent(board: &ExportBoard) -> Vec2 {
+        Self::clue_space(board) + Vec2::splat(board.size as f32)
+    }
+
+    pub fn cell_corner(&self, row: usize, col: usize) -> Vec2 {
+        self.grid_origin + Vec2::new(col as f32, row as f32) * self.cell
+    }
+
+    // Gridline `i` runs along the top or left edge of cell `i`, and line
+    // `size` along the far edge
+    pub fn line_thickness(&self, i: usize, size: usize) -> f32 {
+        if i.is_multiple_of(5) || i == size {
+            self.thin_line * THICK_LINE_FACTOR
+        } else {
+            self.thin_line
+        }
+    }
+
+    // Each clue number's text and the centre of where it is written
+    pub fn clue_labels(&self, board: &ExportBoard) -> Vec<(String, Vec2)> {
+        let char_width = self.font_size * MONO_CHAR_WIDTH;
+        let line_height = self.font_size * LINE_HEIGHT;
+        let mut labels = Vec::new();
+        for (row, runs) in board.row_runs.iter().enumerate() {
+            let y = self.grid_origin.y + (row as f32 + 0.5) * self.cell;
+            for (run, offset) in runs.iter().rev().zip(row_clue_offsets(runs)) {
+                let x = self.grid_origin.x - char_width * (offset + 0.5);
+                labels.push((run.to_string(), round_to_hundredths(Vec2::new(x, y))));
+            }
+        }
+        for (col, runs) in board.col_runs.iter().enumerate() {
+            let x = self.grid_origin.x + (col as f32 + 0.5) * self.cell;
+            for (index, run) in runs.iter().rev().enumerate() {
+                let y = self.grid_origin.y - char_width / 2. - line_height * (index as f32 + 0.5);
+                labels.push((run.to_string(), round_to_hundredths(Vec2::new(x, y))));
+            }
+        }
+        labels
+    }
+
+    // The two strokes of a cross, inset from the cell's corners
+    fn cross_strokes(&self, row: usize, col: usize) -> [(Vec2, Vec2); 2] {
+        let inset = self.cell * 0.25;
+        let min = self.cell_corner(row, col) + Vec2::splat(inset);
+        let max = min + Vec2::splat(self.cell - 2. * inset);
+        [
+            (min, max),
+            (Vec2::new(min.x, max.y), Vec2::new(max.x, min.y)),
+        ]
+    }
+
+    fn cross_width(&self) -> f32 {
+        (self.cell / 10.).max(1.)
+    }
+}
+
+// Keeps coordinates written to SVG files short
+fn round_to_hundredths(point: Vec2) -> Vec2 {
+    (point * 100.).round() / 100.
+}
+
+// Size in pixels of a board's image
+pub fn image_size(board: &ExportBoard, options: &ExportOptions) -> (u32, u32) {
+    let geometry = Geometry::new(board, options);
+    (geometry.width, geometry.height)
+}
+
+fn gray(level: u8) -> String {
+    format!("#{0:02x}{0:02x}{0:02x}", level)
+}
+
+pub fn to_svg(board: &ExportBoard, options: &ExportOptions) -> String {
+    let geometry = Geometry::new(board, options);
+    let (width, height) = (geometry.width, geometry.height);
+    let size = board.size;
+    let mut svg = String::new();
+    // Writing to a string can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width,
+        height,
+        gray(PAPER)
+    );
+
+    let _ = writeln!(svg, r#"<g fill="{}">"#, gray(INK));
+    for row in 0..size {
+        for col in 0..size {
+            if board.mark(row, col) == Some(CellType::Filled) {
+                let corner = geometry.cell_corner(row, col);
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{2}" height="{2}"/>"#,
+                    corner.x, corner.y, geometry.cell
+                );
+            }
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    if options.crosses {
+        let _ = writeln!(
+            svg,
+            r#"<g stroke="{}" stroke-width="{}" stroke-linecap="round">"#,
+            gray(CROSS_INK),
+            geometry.cross_width()
+        );
+        for row in 0..size {
+            for col in 0..size {
+                if board.mark(row, col) != Some(CellType::Cross) {
+                    continue;
+                }
+                for (from, to) in geometry.cross_strokes(row, col) {
+                    let _ = writeln!(
+                        svg,
+                        r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                        from.x, from.y, to.x, to.y
+                    );
+                }
+            }
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+
+    let grid_side = size as f32 * geometry.cell;
+    let _ = writeln!(svg, r#"<g fill="{}">"#, gray(INK));
+    for i in 0..=size {
+        let thickness = geometry.line_thickness(i, size);
+        let start = geometry.grid_origin - Vec2::splat(thickness / 2.);
+        let offset = i as f32 * geometry.cell;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+            start.x + offset,
+            start.y,
+            thickness,
+            grid_side + thickness
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+            start.x,
+            start.y + offset,
+            grid_side + thickness,
+            thickness
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    let _ = writeln!(
+        svg,
+        r#"<g font-family="{}" font-size="{}" text-anchor="middle" fill="{}">"#,
+        FONT_FAMILY,
+        geometry.font_size,
+        gray(INK)
+    );
+    let baseline = geometry.font_size * DIGIT_HEIGHT / 2.;
+    for (text, center) in geometry.clue_labels(board) {
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            center.x,
+            round_to_hundredths(center + Vec2::new(0., baseline)).y,
+            text
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// A grayscale image, one byte per pixel
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![PAPER; width as usize * height as usize],
+        }
+    }
+
+    // Moves a pixel `coverage` of the way to `level`
+    fn blend(&mut self, x: i64, y: i64, level: u8, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let pixel = &mut self.pixels[y as usize * self.width as usize + x as usize];
+        let mixed = *pixel as f32 + (level as f32 - *pixel as f32) * coverage.clamp(0., 1.);
+        *pixel = mixed.round() as u8;
+    }
+
+    // Fills the pixels whose centres fall inside the rectangle
+    fn fill_rect(&mut self, min: Vec2, max: Vec2, level: u8) {
+        let (x0, y0) = (min.x.round() as i64, min.y.round() as i64);
+        let (x1, y1) = (max.x.round() as i64, max.y.round() as i64);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x, y, level, 1.);
+            }
+        }
+    }
+
+    // An antialiased line with round ends
+    fn stroke(&mut self, from: Vec2, to: Vec2, width: f32, level: u8) {
+        let half = width / 2.;
+        let min = from.min(to) - Vec2::splat(half + 1.);
+        let max = from.max(to) + Vec2::splat(half + 1.);
+        let along = to - from;
+        for y in min.y.floor() as i64..max.y.ceil() as i64 {
+            for x in min.x.floor() as i64..max.x.ceil() as i64 {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let t = ((p - from).dot(along) / along.length_squared().max(f32::EPSILON))
+                    .clamp(0., 1.);
+                let distance = p.distance(from + along * t);
+                self.blend(x, y, level, half + 0.5 - distance);
+            }
+        }
+    }
+
+    // Writes a line of text centred on a point
+    fn text(&mut self, font: &FontRef, text: &str, font_size: f32, center: Vec2, level: u8) {
+        let scale = PxScale::from(font_size);
+        let scaled = font.as_scaled(scale);
+        let width: f32 = text
+            .chars()
+            .map(|c| scaled.h_advance(font.glyph_id(c)))
+            .sum();
+        let baseline = center.y + font_size * DIGIT_HEIGHT / 2.;
+        let mut x = center.x - width / 2.;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            let glyph = id.with_scale_and_position(scale, point(x, baseline));
+            x += scaled.h_advance(id);
+            if let Some(outline) = font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let x = bounds.min.x as i64 + gx as i64;
+                    let y = bounds.min.y as i64 + gy as i64;
+                    self.blend(x, y, level, coverage);
+                });
+            }
+        }
+    }
+}
+
+fn rasterize(board: &ExportBoard, options: &ExportOptions) -> Canvas {
+    let geometry = Geometry::new(board, options);
+    let size = board.size;
+    let mut canvas = Canvas::new(geometry.width, geometry.height);
+
+    for row in 0..size {
+        for col in 0..size {
+            let corner = geometry.cell_corner(row, col);
+            match board.mark(row, col) {
+                Some(CellType::Filled) => {
+                    canvas.fill_rect(corner, corner + Vec2::splat(geometry.cell), INK)
+                }
+                Some(CellType::Cross) if options.crosses => {
+                    for (from, to) in geometry.cross_strokes(row, col) {
+                        canvas.stroke(from, to, geometry.cross_width(), CROSS_INK);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let grid_side = size as f32 * geometry.cell;
+    for i in 0..=size {
+        let thickness = geometry.line_thickness(i, size);
+        let start = geometry.grid_origin - Vec2::splat(thickness / 2.);
+        let offset = i as f32 * geometry.cell;
+        let vertical = start + Vec2::new(offset, 0.);
+        canvas.fill_rect(
+            vertical,
+            vertical + Vec2::new(thickness, grid_side + thickness),
+            INK,
+        );
+        let horizontal = start + Vec2::new(0., offset);
+        canvas.fill_rect(
+            horizontal,
+            horizontal + Vec2::new(grid_side + thickness, thickness),
+            INK,
+        );
+    }
+
+    let font = FontRef::try_from_slice(FONT).expect("the bundled font is valid");
+    for (text, center) in geometry.clue_labels(board) {
+        canvas.text(&font, &text, geometry.font_size, center, INK);
+    }
+    canvas
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    // The image would have more than `MAX_PNG_PIXELS` pixels
+    TooLarge { width: u32, height: u32 },
+    Png(png::EncodingError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Io(err) => write!(f, "{}", err),
+            ExportError::TooLarge { width, height } => write!(
+                f,
+                "the image would be {}x{} pixels, more than the {} allowed; \
+                 use a smaller cell size or margin",
+                width, height, MAX_PNG_PIXELS
+            ),
+            ExportError::Png(err) => write!(f, "couldn't encode the PNG: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+// An encoded grayscale PNG, unless it would be too large to draw
+pub fn to_png(board: &ExportBoard, options: &ExportOptions) -> Result<Vec<u8>, ExportError> {
+    let (width, height) = image_size(board, options);
+    let pixels = (width as usize).checked_mul(height as usize);
+    if !matches!(pixels, Some(pixels) if pixels <= MAX_PNG_PIXELS) {
+        return Err(ExportError::TooLarge { width, height });
+    }
+    let canvas = rasterize(board, options);
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, canvas.width, canvas.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(ExportError::Png)?;
+    writer
+        .write_image_data(&canvas.pixels)
+        .map_err(ExportError::Png)?;
+    writer.finish().map_err(ExportError::Png)?;
+    Ok(bytes)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Svg,
+    Png,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Svg => "svg",
+            Format::Png => "png",
+        }
+    }
+}
+
+pub fn write(
+    board: &ExportBoard,
+    options: &ExportOptions,
+    format: Format,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let bytes = match format {
+        Format::Svg => to_svg(board, options).into_bytes(),
+        Format::Png => to_png(board, options)?,
+    };
+    fs::write(path, bytes).map_err(ExportError::Io)
+}
+
+// A pack to draw, one file per puzzle and format
+pub struct ExportArgs {
+    pub dir: PathBuf,
+    pub formats: Vec<Format>,
+    pub options: ExportOptions,
+    // Also draws each puzzle's solution, in a file of its own
+    pub solutions: bool,
+}
+
+// Reads `--export <dir> [--format svg|png|both] [--cell-size n] [--margin n]
+// [--no-crosses] [--solutions]` from the command line
+pub fn options_from_args() -> Option<ExportArgs> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--export")?;
+    let value = |name: &str| {
+        let at = args.iter().position(|arg| arg == name)?;
+        args.get(at + 1)
+    };
+    let defaults = ExportOptions::default();
+    Some(ExportArgs {
+        dir: args.get(flag + 1).map_or_else(|| ".".into(), PathBuf::from),
+        formats: match value("--format").map(String::as_str) {
+            Some("png") => vec![Format::Png],
+            Some("both") => vec![Format::Svg, Format::Png],
+            _ => vec![Format::Svg],
+        },
+        options: ExportOptions {
+            cell_size: value("--cell-size")
+                .and_then(|size| size.parse().ok())
+                .unwrap_or(defaults.cell_size),
+            margin: value("--margin")
+                .and_then(|margin| margin.parse().ok())
+                .unwrap_or(defaults.margin),
+            crosses: !args.iter().any(|arg| arg == "--no-crosses"),
+        },
+        solutions: args.iter().any(|arg| arg == "--solutions"),
+    })
+}
+
+// Puzzle codes read from stdin, one per line, such as the output of
+// `--generate`, each with its position among the non-blank lines. Codes that
+// don't parse are reported and skipped.
+pub(crate) fn codes_from_stdin() -> impl Iterator<Item = (usize, PuzzleCode)> {
+    let lines = io::stdin().lock().lines().map_while(Result::ok);
+    let codes = lines.filter(|line| !line.trim().is_empty());
+    codes
+        .enumerate()
+        .filter_map(|(index, line)| match line.trim().parse() {
+            Ok(code) => Some((index, code)),
+            Err(err) => {
+                eprintln!("Skipping line {}: {}", index + 1, err);
+                None
+            }
+        })
+}
+
+// Draws every puzzle code read from stdin. Files are numbered in the order
+// the codes come.
+pub fn run(args: &ExportArgs) {
+    if let Err(err) = fs::create_dir_all(&args.dir) {
+        eprintln!("Couldn't create {}: {}", args.dir.display(), err);
+        return;
+    }
+    let mut written = 0;
+    for (index, code) in codes_from_stdin() {
+        let solution = code.solution();
+        let mut boards = vec![(
+            format!("puzzle-{:03}", index + 1),
+            ExportBoard::blank(&solution),
+        )];
+        if args.solutions {
+            boards.push((
+                format!("puzzle-{:03}-solution", index + 1),
+                ExportBoard::solved(&solution),
+            ));
+        }
+        for (name, board) in &boards {
+            for format in &args.formats {
+                let path = args.dir.join(name).with_extension(format.extension());
+                match write(board, &args.options, *format, &path) {
+                    Ok(()) => written += 1,
+                    Err(err) => eprintln!("Couldn't write {}: {}", path.display(), err),
+                }
+            }
+        }
+    }
+    eprintln!("Wrote {} files to {}", written, args.dir.display());
+}
+
+// `E` saves the board as it stands, as an SVG and a PNG, to the pictures
+// folder (or the working directory). The files are drawn off the main thread,
+// so large boards don't stall the game, and reported once written.
+pub fn handle_export_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    puzzle: Res<Puzzle>,
+    task_pool: Res<AsyncComputeTaskPool>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::E) {
+        return;
+    }
+    let dir = dirs::picture_dir().unwrap_or_else(|| ".".into());
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let board = ExportBoard::from_board(&puzzle.board);
+    task_pool
+        .spawn(async move {
+            for format in [Format::Svg, Format::Png] {
+                let path = dir
+                    .join(format!("nonogram-{}", stamp))
+                    .with_extension(format.extension());
+                match write(&board, &ExportOptions::default(), format, &path) {
+                    Ok(()) => eprintln!("Exported the board to {}", path.display()),
+                    Err(err) => {
+                        eprintln!("Couldn't export the board to {}: {}", path.display(), err)
+                    }
+                }
+            }
+        })
+        .detach();
+}
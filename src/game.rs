@@ -0,0 +1,1041 @@
+use bevy::core::Stopwatch;
+use bevy::prelude::*;
+use bevy::render::texture::DEFAULT_IMAGE_HANDLE;
+use bevy::window::{WindowResized, WindowScaleFactorChanged};
+
+use crate::board::BitBoard;
+use crate::board_texture::{mark_image, BoardTexture, TEXTURE_BOARD_SIZE};
+use crate::camera::{self, visible_world_rect, window_to_world};
+use crate::clipboard::Clipboard;
+use crate::code::{PuzzleCode, MAX_PUZZLE_SIZE};
+use crate::diagnostics::ClueProblem;
+use crate::export;
+use crate::layout::{row_clue_offsets, wrap_code, BoardLayout, ClueExtents, HUD_FONT_SIZE};
+use crate::menu::PuzzleProblems;
+use crate::model::Board;
+use crate::replay::{self, PlaybackText, Replay, ReplayPlayback};
+use crate::settings::{MistakeMode, Settings};
+use crate::theme::{Theme, ThemeColor};
+use crate::win::{RevealPicture, Solution};
+use crate::{AppState, MainCamera, NonogramConfig, PuzzleSource};
+
+pub use crate::model::{BoardLine, CellChanged, CellType, Layer, Mark};
+
+// The board: starting and ending games, the player's edits, and everything
+// drawn while a game is in progress. Also adds `AppState`, starting in the
+// config's initial state, and the default settings unless some were
+// inserted first.
+pub struct NonogramPlugin;
+
+impl Plugin for NonogramPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app
+            .world
+            .get_resource_or_insert_with(NonogramConfig::default)
+            .clone();
+        app.world.get_resource_or_insert_with(|| Settings {
+            board_size: config.board_size,
+            ..Default::default()
+        });
+        app.add_state(config.initial_state)
+            .insert_resource(config.theme)
+            .init_resource::<Clipboard>()
+            .add_event::<CellChanged>()
+            .add_event::<LineCompleted>()
+            .add_event::<LineReopened>()
+            .add_event::<PuzzleSolved>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::InGame)
+                    .with_system(setup_game)
+                    .with_system(camera::reset_camera),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(handle_mouse_clicks.label("edit_board").before("sync_cells"))
+                    .with_system(handle_edit_keys.label("edit_board").before("sync_cells"))
+                    .with_system(replay::play_back.label("edit_board").before("sync_cells"))
+                    .with_system(replay::record_actions.after("edit_board"))
+                    .with_system(handle_auto_cross_toggle.before("sync_cells"))
+                    .with_system(sync_cells.label("sync_cells"))
+                    .with_system(track_lines.label("track_lines").after("sync_cells"))
+                    .with_system(update_cell_sprites.after("sync_cells"))
+                    .with_system(draw_board_texture.after("sync_cells"))
+                    .with_system(recolor_board.before("sync_cells"))
+                    .with_system(apply_auto_cross.after("track_lines"))
+                    .with_system(check_win.after("track_lines"))
+                    .with_system(update_tentative_indicator)
+                    .with_system(handle_copy_code)
+                    .with_system(export::handle_export_key)
+                    .with_system(replay::handle_save_key)
+                    .with_system(replay::update_playback_text)
+                    .with_system(tick_timer.after("edit_board"))
+                    .with_system(camera::zoom_camera)
+                    .with_system(camera::pan_camera)
+                    .with_system(camera::handle_view_reset)
+                    .with_system(update_layout.before("pin_clues"))
+                    .with_system(pin_clues.label("pin_clues")),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(close_game));
+    }
+}
+
+// Everything spawned for a game, which `close_game` despawns
+#[derive(Component)]
+pub struct Game;
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component)]
+struct TimerText;
+
+// The puzzle the next game starts with, chosen in a menu. Without one, games
+// start with the config's puzzle.
+pub struct NextPuzzle(pub PuzzleCode);
+
+// Time spent on the current puzzle, which only runs while it is on screen
+#[derive(Default)]
+pub struct GameTimer(pub Stopwatch);
+
+fn hud_text(code: &PuzzleCode) -> String {
+    format!("CODE {}   (C TO COPY)", code)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_game(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    windows: Res<Windows>,
+    config: Res<NonogramConfig>,
+    theme: Res<Theme>,
+    next: Option<Res<NextPuzzle>>,
+    settings: Res<Settings>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    let code = match next {
+        Some(next) => {
+            commands.remove_resource::<NextPuzzle>();
+            next.0.clone()
+        }
+        None => config.puzzle_code(),
+    };
+    // Codes made in code rather than parsed can be any size
+    if !(1..=MAX_PUZZLE_SIZE).contains(&code.size()) {
+        commands.insert_resource(PuzzleProblems {
+            source: format!("CODE {}", code),
+            problems: vec![ClueProblem::InvalidSize(code.size()).to_string()],
+        });
+        app_state.set(AppState::PuzzleError).unwrap();
+        return;
+    }
+    // The config's puzzle file is revealed in its colors, also on restarting
+    if let PuzzleSource::File(puzzle) = &config.puzzle {
+        if let Some(picture) = puzzle.picture.as_ref().filter(|_| puzzle.code() == code) {
+            commands.insert_resource(picture.clone());
+        }
+    }
+    commands.insert_resource(code.clone());
+    commands.insert_resource(GameTimer::default());
+    commands.insert_resource(Replay::new(code.clone()));
+
+    let win = windows.get_primary().expect("no primary window");
+    let hud_text = hud_text(&code);
+    let mut puzzle = Puzzle::new(
+        &mut commands,
+        &asset_server,
+        &mut images,
+        &theme,
+        code.solution(),
+        Vec2::new(win.width(), win.height()),
+        hud_text.len(),
+        settings.clue_scale,
+    );
+    if settings.auto_cross {
+        puzzle.board.set_auto_cross(true);
+    }
+
+    // Code display, so the board can be shared and replayed
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                wrap_code(&hud_text, puzzle.grid.layout.hud_columns),
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: HUD_FONT_SIZE,
+                    color: theme.hud,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(Hud)
+        .insert(ThemeColor::Hud)
+        .insert(Game);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: HUD_FONT_SIZE,
+                    color: theme.tentative,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(TentativeIndicator)
+        .insert(ThemeColor::Tentative)
+        .insert(Game);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                format_time(0),
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: HUD_FONT_SIZE,
+                    color: theme.hud,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(TimerText)
+        .insert(ThemeColor::Hud)
+        .insert(Game);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: HUD_FONT_SIZE,
+                    color: theme.hud,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(PlaybackText)
+        .insert(ThemeColor::Hud)
+        .insert(Game);
+
+    commands.insert_resource(puzzle);
+}
+
+// Minutes and seconds, or hours too for very long games
+pub(crate) fn format_time(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+// Follows the replay's clock while one is played back
+fn tick_timer(
+    time: Res<Time>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut timer: ResMut<GameTimer>,
+    mut query: Query<&mut Text, With<TimerText>>,
+) {
+    let before = timer.0.elapsed().as_secs();
+    match playback {
+        Some(playback) => timer.0.set_elapsed(playback.clock()),
+        None => {
+            timer.0.tick(time.delta());
+        }
+    }
+    let after = timer.0.elapsed().as_secs();
+    if after != before {
+        for mut text in query.iter_mut() {
+            text.sections[0].value = format_time(after);
+        }
+    }
+}
+
+// Flips the auto-cross setting, which is remembered for later games too
+fn handle_auto_cross_toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut puzzle: ResMut<Puzzle>,
+) {
+    if keyboard_input.just_pressed(KeyCode::A) {
+        settings.auto_cross = !settings.auto_cross;
+        puzzle.board.set_auto_cross(settings.auto_cross);
+    }
+}
+
+fn handle_copy_code(
+    keyboard_input: Res<Input<KeyCode>>,
+    code: Res<PuzzleCode>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    if keyboard_input.just_pressed(KeyCode::C) {
+        clipboard.set_text(code.to_string());
+    }
+}
+
+pub fn close_game(mut commands: Commands, query: Query<Entity, With<Game>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Puzzle>();
+    commands.remove_resource::<PuzzleCode>();
+    commands.remove_resource::<GameTimer>();
+    commands.remove_resource::<RevealPicture>();
+    commands.remove_resource::<Replay>();
+    commands.remove_resource::<ReplayPlayback>();
+}
+
+// A clue number, which slides along its axis to stay on screen while the
+// grid is scrolled past the visible edge. Row clues are placed `offset`
+// characters from the grid, and column clues are stacked by `index`, both
+// counting outwards from the grid.
+#[derive(Component)]
+enum Clue {
+    Row { row: usize, offset: f32 },
+    Col { col: usize, index: usize },
+}
+
+// Screen-space gap kept between pinned clues and the edge of the view
+const CLUE_PIN_MARGIN: f32 = 12.;
+
+// Sent when a line's filled cells come to match its clues
+pub struct LineCompleted(pub BoardLine);
+
+// Sent when a completed line's filled cells stop matching its clues
+struct LineReopened(BoardLine);
+
+pub struct PuzzleSolved;
+
+// The puzzle being played, and how it is drawn
+pub struct Puzzle {
+    pub board: Board,
+    pub grid: Grid,
+    clue_scale: f32,
+}
+
+impl Puzzle {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        commands: &mut Commands,
+        asset_server: &Res<AssetServer>,
+        images: &mut Assets<Image>,
+        theme: &Theme,
+        solution: BitBoard,
+        window_size: Vec2,
+        hud_len: usize,
+        clue_scale: f32,
+    ) -> Self {
+        let board = Board::new(solution);
+        let layout = Self::layout(
+            board.row_runs(),
+            board.col_runs(),
+            window_size,
+            hud_len,
+            clue_scale,
+        );
+        let grid = Grid::new(
+            commands,
+            images,
+            theme,
+            asset_server.load("textures/cross.png"),
+            layout,
+        );
+        let puzzle = Self {
+            board,
+            grid,
+            clue_scale,
+        };
+
+        // Monospaced, so digits line up and clue widths can be measured
+        let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+        let text_style = TextStyle {
+            font,
+            font_size: puzzle.grid.layout.clue_font_size,
+            color: theme.clue,
+        };
+        let text_alignment = TextAlignment {
+            vertical: VerticalAlign::Center,
+            horizontal: HorizontalAlign::Center,
+        };
+
+        let clues = puzzle
+            .board
+            .row_runs()
+            .iter()
+            .enumerate()
+            .flat_map(|(row, runs)| {
+                row_clue_offsets(runs)
+                    .into_iter()
+                    .zip(runs.iter().rev())
+                    .map(move |(offset, run)| (Clue::Row { row, offset }, *run))
+            });
+        let clues = clues.chain(puzzle.board.col_runs().iter().enumerate().flat_map(
+            |(col, runs)| {
+                runs.iter()
+                    .rev()
+                    .enumerate()
+                    .map(move |(index, run)| (Clue::Col { col, index }, *run))
+            },
+        ));
+        for (clue, run) in clues {
+            commands
+                .spawn_bundle(Text2dBundle {
+                    text: Text::with_section(run.to_string(), text_style.clone(), text_alignment),
+                    transform: Transform::from_translation(
+                        clue_position(&puzzle.grid.layout, &clue).extend(10.),
+                    ),
+                    ..Default::default()
+                })
+                .insert(clue)
+                .insert(ThemeColor::Clue)
+                .insert(Game);
+        }
+
+        puzzle
+    }
+
+    // Lays the board out for a window, leaving room for the longest clue lists
+    fn layout(
+        row_runs: &[Vec<usize>],
+        col_runs: &[Vec<usize>],
+        window_size: Vec2,
+        hud_len: usize,
+        clue_scale: f32,
+    ) -> BoardLayout {
+        BoardLayout::new(
+            window_size,
+            row_runs.len(),
+            ClueExtents::measure(row_runs, col_runs),
+            hud_len,
+            clue_scale,
+        )
+    }
+
+    pub fn layout_for(&self, window_size: Vec2, hud_len: usize) -> BoardLayout {
+        Self::layout(
+            self.board.row_runs(),
+            self.board.col_runs(),
+            window_size,
+            hud_len,
+            self.clue_scale,
+        )
+    }
+}
+
+fn clue_position(layout: &BoardLayout, clue: &Clue) -> Vec2 {
+    match *clue {
+        Clue::Row { row, offset } => layout.row_clue_position(row, offset),
+        Clue::Col { col, index } => layout.col_clue_position(col, index),
+    }
+}
+
+const GRID_THICKNESS: f32 = 0.5;
+
+// The mark in a cell, kept in step with the grid by `sync_cells`
+#[derive(Component, Copy, Clone, Default)]
+pub struct CellState(pub Option<Mark>);
+
+#[derive(Component, Copy, Clone)]
+pub struct GridPos {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Component)]
+struct GridBackground;
+
+// The line after cell `index`, running vertically or horizontally
+#[derive(Component)]
+struct GridLine {
+    index: usize,
+    vertical: bool,
+}
+
+// The board's marks, with one entity per cell. Small boards give every cell
+// a sprite, while large boards draw all of them into one texture.
+pub struct Grid {
+    layout: BoardLayout,
+    // Cell entities, row by row
+    entities: Vec<Entity>,
+    texture: Option<(BoardTexture, Handle<Image>)>,
+
+    cross_handle: Handle<Image>,
+    // Hatched fills and framed crosses, for highlighted mistakes
+    mistake_fill_handle: Handle<Image>,
+    mistake_cross_handle: Handle<Image>,
+}
+
+impl Grid {
+    pub fn new(
+        commands: &mut Commands,
+        images: &mut Assets<Image>,
+        theme: &Theme,
+        cross_handle: Handle<Image>,
+        layout: BoardLayout,
+    ) -> Self {
+        let size = layout.size;
+        let texture = if size >= TEXTURE_BOARD_SIZE {
+            let texture = BoardTexture::new(size);
+            let image = images.add(texture.create_image(theme));
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::ONE),
+                        ..Default::default()
+                    },
+                    texture: image.clone(),
+                    transform: Self::background_transform(&layout),
+                    ..Default::default()
+                })
+                .insert(GridBackground)
+                .insert(Game);
+            Some((texture, image))
+        } else {
+            Self::spawn_sprite_grid(commands, theme, &layout);
+            None
+        };
+
+        let mut entities = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let pos = GridPos { row, col };
+                let mut cell = commands.spawn();
+                cell.insert(pos).insert(CellState::default()).insert(Game);
+                if texture.is_none() {
+                    cell.insert_bundle(SpriteBundle {
+                        transform: Self::cell_transform(&layout, pos),
+                        visibility: Visibility { is_visible: false },
+                        ..Default::default()
+                    });
+                }
+                entities.push(cell.id());
+            }
+        }
+
+        Self {
+            layout,
+            entities,
+            texture,
+            cross_handle,
+            mistake_fill_handle: images.add(mark_image(CellType::Filled, true)),
+            mistake_cross_handle: images.add(mark_image(CellType::Cross, true)),
+        }
+    }
+
+    fn spawn_sprite_grid(commands: &mut Commands, theme: &Theme, layout: &BoardLayout) {
+        // Background
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: theme.grid_background,
+                    ..Default::default()
+                },
+                transform: Self::background_transform(layout),
+                ..Default::default()
+            })
+            .insert(GridBackground)
+            .insert(ThemeColor::GridBackground)
+            .insert(Game);
+
+        // Grid
+        for i in 0..layout.size - 1 {
+            let sprite = Sprite {
+                color: theme.gridline,
+                ..Default::default()
+            };
+
+            for vertical in [true, false] {
+                let line = GridLine { index: i, vertical };
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: sprite.clone(),
+                        transform: Self::line_transform(layout, &line),
+                        ..Default::default()
+                    })
+                    .insert(line)
+                    .insert(ThemeColor::Gridline)
+                    .insert(Game);
+            }
+        }
+    }
+
+    fn background_transform(layout: &BoardLayout) -> Transform {
+        Transform {
+            translation: (layout.center - Vec2::splat(GRID_THICKNESS / 2.)).extend(0.),
+            scale: Vec3::new(
+                layout.grid_size - GRID_THICKNESS,
+                layout.grid_size - GRID_THICKNESS,
+                0.,
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn line_transform(layout: &BoardLayout, line: &GridLine) -> Transform {
+        let thickness = GRID_THICKNESS
+            * if (line.index + 1).is_multiple_of(5) {
+                3.
+            } else {
+                1.
+            };
+        let offset = layout.line_offset(line.index) - layout.grid_size / 2. - thickness / 2.;
+        if line.vertical {
+            Transform {
+                translation: Vec3::new(layout.center.x + offset, layout.center.y, 10.),
+                scale: Vec3::new(thickness, layout.grid_size, 0.),
+                ..Default::default()
+            }
+        } else {
+            Transform {
+                translation: Vec3::new(layout.center.x, layout.center.y + offset, 10.),
+                scale: Vec3::new(layout.grid_size, thickness, 0.),
+                ..Default::default()
+            }
+        }
+    }
+
+    fn cell_transform(layout: &BoardLayout, pos: GridPos) -> Transform {
+        let center = layout.cell_center(pos.row, pos.col) - Vec2::splat(GRID_THICKNESS / 2.);
+        Transform::from_translation(center.extend(1.))
+    }
+
+    fn cell_sprite_size(layout: &BoardLayout, cell_type: CellType) -> Vec2 {
+        let size = layout.cell_size() - GRID_THICKNESS;
+        match cell_type {
+            CellType::Filled | CellType::Cross => Vec2::splat(size),
+            CellType::Dot => Vec2::splat(size * 0.3),
+        }
+    }
+
+    pub fn entity_at(&self, row: usize, col: usize) -> Entity {
+        self.entities[row * self.layout.size + col]
+    }
+
+    // If possible, gives the row and col that contains a world pos
+    pub fn point_coords(&self, pos: Vec2) -> Option<(usize, usize)> {
+        self.layout.point_coords(pos)
+    }
+
+    // Centre and side of the square a cell's fill covers, for effects drawn
+    // over the board
+    pub fn cell_square(&self, row: usize, col: usize) -> (Vec2, f32) {
+        let pos = GridPos { row, col };
+        let center = Self::cell_transform(&self.layout, pos)
+            .translation
+            .truncate();
+        (
+            center,
+            Self::cell_sprite_size(&self.layout, CellType::Filled).x,
+        )
+    }
+
+    // Takes every mark off a large board's texture, leaving the empty grid.
+    // Boards with cell sprites are left as they are.
+    pub fn erase_texture(&self, images: &mut Assets<Image>, theme: &Theme) {
+        if let Some((texture, image)) = &self.texture {
+            if let Some(image) = images.get_mut(image.clone()) {
+                *image = texture.create_image(theme);
+            }
+        }
+    }
+}
+
+// Copies the marks changed since the last frame onto the cell entities, and
+// announces each change
+fn sync_cells(
+    mut puzzle: ResMut<Puzzle>,
+    mut cells: Query<&mut CellState>,
+    mut cell_changed: EventWriter<CellChanged>,
+) {
+    if !puzzle.board.has_changes() {
+        return;
+    }
+    for change in puzzle.board.take_changes() {
+        if let Ok(mut state) = cells.get_mut(puzzle.grid.entity_at(change.row, change.col)) {
+            state.0 = change.mark;
+        }
+        cell_changed.send(change);
+    }
+}
+
+// Works out which lines the changed cells completed or reopened, and whether
+// the whole puzzle is now solved
+fn track_lines(
+    mut cell_changed: EventReader<CellChanged>,
+    mut puzzle: ResMut<Puzzle>,
+    mut line_completed: EventWriter<LineCompleted>,
+    mut line_reopened: EventWriter<LineReopened>,
+    mut puzzle_solved: EventWriter<PuzzleSolved>,
+) {
+    let mut any_changed = false;
+    for change in cell_changed.iter() {
+        any_changed = true;
+        for line in [BoardLine::Row(change.row), BoardLine::Col(change.col)] {
+            match puzzle.board.update_line(line) {
+                Some(true) => line_completed.send(LineCompleted(line)),
+                Some(false) => line_reopened.send(LineReopened(line)),
+                None => {}
+            }
+        }
+    }
+    if any_changed && puzzle.board.is_solved() {
+        puzzle_solved.send(PuzzleSolved);
+    }
+}
+
+fn apply_auto_cross(
+    settings: Res<Settings>,
+    mut line_completed: EventReader<LineCompleted>,
+    mut line_reopened: EventReader<LineReopened>,
+    mut puzzle: ResMut<Puzzle>,
+) {
+    let completed = line_completed.iter().map(|event| event.0);
+    let lines: Vec<BoardLine> = completed
+        .chain(line_reopened.iter().map(|event| event.0))
+        .collect();
+    if !settings.auto_cross || lines.is_empty() {
+        return;
+    }
+    for line in lines {
+        puzzle.board.refresh_line_auto_cross(line);
+    }
+}
+
+// Shows, hides and restyles cell sprites on boards small enough to have them
+#[allow(clippy::type_complexity)]
+fn update_cell_sprites(
+    puzzle: Res<Puzzle>,
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    mut cells: Query<
+        (
+            &GridPos,
+            &CellState,
+            &mut Sprite,
+            &mut Handle<Image>,
+            &mut Visibility,
+        ),
+        Changed<CellState>,
+    >,
+) {
+    let layout = &puzzle.grid.layout;
+    let highlight = settings.mistakes == MistakeMode::Highlight;
+    for (pos, state, mut sprite, mut texture, mut visibility) in cells.iter_mut() {
+        visibility.is_visible = state.0.is_some();
+        if let Some(mark) = state.0 {
+            let mistake = highlight && puzzle.board.is_mistake(pos.row, pos.col, state.0);
+            sprite.color = theme.cell_color(mark, mistake);
+            sprite.custom_size = Some(Grid::cell_sprite_size(layout, mark.cell_type));
+            *texture = match (mark.cell_type, mistake) {
+                (CellType::Filled, true) => puzzle.grid.mistake_fill_handle.clone(),
+                (CellType::Cross, true) => puzzle.grid.mistake_cross_handle.clone(),
+                (CellType::Cross, false) => puzzle.grid.cross_handle.clone(),
+                (CellType::Filled, false) | (CellType::Dot, _) => DEFAULT_IMAGE_HANDLE.typed(),
+            };
+        }
+    }
+}
+
+// Recolors the marks when the theme changes, by restyling every cell sprite
+// or redrawing the whole board texture. Everything else on the board is
+// tagged with a `ThemeColor`.
+fn recolor_board(
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    puzzle: Res<Puzzle>,
+    mut images: ResMut<Assets<Image>>,
+    mut cells: Query<&mut CellState>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    let (texture, image) = match &puzzle.grid.texture {
+        Some(texture) => texture,
+        None => {
+            for mut state in cells.iter_mut() {
+                state.set_changed();
+            }
+            return;
+        }
+    };
+    if let Some(image) = images.get_mut(image.clone()) {
+        *image = texture.create_image(&theme);
+        let highlight = settings.mistakes == MistakeMode::Highlight;
+        let size = puzzle.board.size();
+        for row in 0..size {
+            for col in 0..size {
+                let mark = puzzle.board.mark_at(row, col);
+                if mark.is_some() {
+                    let mistake = highlight && puzzle.board.is_mistake(row, col, mark);
+                    texture.draw_cell(&mut image.data, &theme, row, col, mark, mistake);
+                }
+            }
+        }
+    }
+}
+
+// Draws changed cells into the board texture on boards too large for sprites
+fn draw_board_texture(
+    mut cell_changed: EventReader<CellChanged>,
+    puzzle: Res<Puzzle>,
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let (texture, image) = match &puzzle.grid.texture {
+        Some(texture) => texture,
+        None => return,
+    };
+    let mut changes = cell_changed.iter().peekable();
+    if changes.peek().is_none() {
+        return;
+    }
+    let highlight = settings.mistakes == MistakeMode::Highlight;
+    if let Some(image) = images.get_mut(image.clone()) {
+        for change in changes {
+            let mistake = highlight && puzzle.board.is_mistake(change.row, change.col, change.mark);
+            texture.draw_cell(
+                &mut image.data,
+                &theme,
+                change.row,
+                change.col,
+                change.mark,
+                mistake,
+            );
+        }
+    }
+}
+
+fn handle_mouse_clicks(
+    mouse_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    settings: Res<Settings>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut puzzle: ResMut<Puzzle>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    let win = windows.get_primary().expect("no primary window");
+    let (camera, camera_transform) = camera.single();
+
+    let left_clicked = mouse_input.just_pressed(MouseButton::Left);
+    let right_clicked = mouse_input.just_pressed(MouseButton::Right);
+    if left_clicked || right_clicked {
+        if let Some(click_pos) = win.cursor_position() {
+            let world_pos = window_to_world(win, camera, camera_transform, click_pos);
+
+            if let Some((row, col)) = puzzle.grid.point_coords(world_pos) {
+                let shift = keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+                let cross_clicked = if settings.swap_mouse_buttons {
+                    left_clicked
+                } else {
+                    right_clicked
+                };
+                let cell_type = if cross_clicked {
+                    CellType::Cross
+                } else if shift {
+                    CellType::Dot
+                } else {
+                    CellType::Filled
+                };
+                puzzle.board.toggle_mark(row, col, cell_type);
+            }
+        }
+    }
+}
+
+// Undo/redo, and switching, committing or discarding the tentative layer
+fn handle_edit_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut puzzle: ResMut<Puzzle>,
+) {
+    if playback.is_some() {
+        return;
+    }
+    let ctrl = keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    let shift = keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+
+    let board = &mut puzzle.board;
+    if ctrl && keyboard_input.just_pressed(KeyCode::Z) {
+        if shift {
+            board.redo();
+        } else {
+            board.undo();
+        }
+    } else if ctrl && keyboard_input.just_pressed(KeyCode::Y) {
+        board.redo();
+    } else if keyboard_input.just_pressed(KeyCode::T) {
+        board.tentative = !board.tentative;
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        board.commit_tentative();
+    } else if keyboard_input.any_just_pressed([KeyCode::Back, KeyCode::Delete]) {
+        board.discard_tentative();
+    }
+}
+
+fn check_win(
+    mut commands: Commands,
+    mut puzzle_solved: EventReader<PuzzleSolved>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if puzzle_solved.iter().next().is_some() {
+        commands.insert_resource(Solution { revealed: false });
+        app_state.push(AppState::Solved).unwrap();
+    }
+}
+
+#[derive(Component)]
+struct TentativeIndicator;
+
+fn update_tentative_indicator(
+    puzzle: Res<Puzzle>,
+    mut query: Query<&mut Text, With<TentativeIndicator>>,
+) {
+    if !puzzle.is_changed() {
+        return;
+    }
+    let value = if puzzle.board.tentative {
+        "TENTATIVE - ENTER TO COMMIT, BACKSPACE TO DISCARD"
+    } else {
+        ""
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.to_string();
+        }
+    }
+}
+
+// Keeps the row clues inside the left edge of the view and the column clues
+// inside the top edge, but never further in than the opposite side of the grid.
+// Also applies the layout's clue font size, which changes with the grid size.
+fn pin_clues(
+    windows: Res<Windows>,
+    puzzle: Res<Puzzle>,
+    camera: Query<(&Camera, &GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+    mut clues: Query<(&Clue, &mut Transform, &mut Text)>,
+) {
+    let win = windows.get_primary().expect("no primary window");
+    let (camera, camera_transform, projection) = camera.single();
+    let (view_min, view_max) = visible_world_rect(win, camera, camera_transform);
+    let margin = CLUE_PIN_MARGIN * projection.scale;
+    let layout = &puzzle.grid.layout;
+
+    let mut row_extent = f32::INFINITY;
+    let mut col_extent = f32::NEG_INFINITY;
+    for (clue, _, _) in clues.iter() {
+        let pos = clue_position(layout, clue);
+        match clue {
+            Clue::Row { .. } => row_extent = row_extent.min(pos.x),
+            Clue::Col { .. } => col_extent = col_extent.max(pos.y),
+        }
+    }
+
+    let shift_x = (view_min.x + margin - row_extent).clamp(0., layout.grid_size);
+    let shift_y = (view_max.y - margin - col_extent).clamp(-layout.grid_size, 0.);
+    for (clue, mut transform, mut text) in clues.iter_mut() {
+        if text.sections[0].style.font_size != layout.clue_font_size {
+            text.sections[0].style.font_size = layout.clue_font_size;
+        }
+        let pos = clue_position(layout, clue);
+        let shift = match clue {
+            Clue::Row { .. } => Vec2::new(shift_x, 0.),
+            Clue::Col { .. } => Vec2::new(0., shift_y),
+        };
+        transform.translation = (pos + shift).extend(transform.translation.z);
+    }
+}
+
+// Lays the board out again whenever the window's size or scale factor changes
+#[allow(clippy::type_complexity)]
+fn update_layout(
+    mut resized_events: EventReader<WindowResized>,
+    mut scale_events: EventReader<WindowScaleFactorChanged>,
+    windows: Res<Windows>,
+    code: Res<PuzzleCode>,
+    mut puzzle: ResMut<Puzzle>,
+    mut queries: QuerySet<(
+        QueryState<&mut Transform, With<GridBackground>>,
+        QueryState<(&GridLine, &mut Transform)>,
+        QueryState<(&GridPos, &CellState, &mut Transform, &mut Sprite)>,
+    )>,
+    mut hud: Query<&mut Text, With<Hud>>,
+) {
+    let win = windows.get_primary().expect("no primary window");
+    let primary_changed = resized_events.iter().any(|event| event.id == win.id())
+        | scale_events.iter().any(|event| event.id == win.id());
+    if !primary_changed {
+        return;
+    }
+
+    let hud_text = hud_text(&code);
+    let layout = puzzle.layout_for(Vec2::new(win.width(), win.height()), hud_text.len());
+    if layout == puzzle.grid.layout {
+        return;
+    }
+    puzzle.grid.layout = layout;
+
+    for mut transform in queries.q0().iter_mut() {
+        *transform = Grid::background_transform(&layout);
+    }
+    for (line, mut transform) in queries.q1().iter_mut() {
+        *transform = Grid::line_transform(&layout, line);
+    }
+    for (pos, state, mut transform, mut sprite) in queries.q2().iter_mut() {
+        *transform = Grid::cell_transform(&layout, *pos);
+        if let Some(mark) = state.0 {
+            sprite.custom_size = Some(Grid::cell_sprite_size(&layout, mark.cell_type));
+        }
+    }
+    for mut text in hud.iter_mut() {
+        text.sections[0].value = wrap_code(&hud_text, layout.hud_columns);
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::board::clues;
+use crate::code::{PuzzleCode, MAX_PUZZLE_SIZE};
+use crate::solver::{Solution, Solver};
+
+// Each puzzle gives up after this many rejected boards
+const MAX_ATTEMPTS: u64 = 1_000;
+
+const DEFAULT_PACK_SIZE: usize = 10;
+
+// A batch of puzzles to generate. Every puzzle's seeds are derived from the
+// base seed and its index alone, so a pack is the same however many threads
+// build it.
+pub struct PackOptions {
+    pub count: usize,
+    pub size: usize,
+    pub seed: u64,
+    pub threads: Option<usize>,
+}
+
+#[derive(Default)]
+pub struct PackStats {
+    pub generated: usize,
+    pub failed: usize,
+    pub boards_tried: u64,
+    pub rejected_multiple: u64,
+    pub rejected_too_hard: u64,
+    pub elapsed: Duration,
+}
+
+impl PackStats {
+    fn add(&mut self, result: &TaskResult) {
+        match result.code {
+            Some(_) => self.generated += 1,
+            None => self.failed += 1,
+        }
+        self.boards_tried += result.boards_tried;
+        self.rejected_multiple += result.rejected_multiple;
+        self.rejected_too_hard += result.rejected_too_hard;
+    }
+}
+
+#[derive(Default)]
+struct TaskResult {
+    code: Option<PuzzleCode>,
+    boards_tried: u64,
+    rejected_multiple: u64,
+    rejected_too_hard: u64,
+}
+
+// SplitMix64, which spreads nearby inputs over the whole range of seeds
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn board_seed(base: u64, index: usize, attempt: u64) -> u64 {
+    mix(mix(base ^ mix(index as u64)) ^ attempt)
+}
+
+// Tries seeded boards until one has a unique solution, so the puzzle can be
+// solved without guessing which of several answers was meant
+fn generate_one(size: usize, base: u64, index: usize) -> TaskResult {
+    let mut result = TaskResult::default();
+    for attempt in 0..MAX_ATTEMPTS {
+        let code = PuzzleCode::Seeded {
+            size,
+            seed: board_seed(base, index, attempt),
+        };
+        let (row_runs, col_runs) = clues(&code.solution());
+        result.boards_tried += 1;
+        match Solver::new(&row_runs, &col_runs).solve() {
+            Solution::Unique(_) => {
+                result.code = Some(code);
+                break;
+            }
+            Solution::Multiple => result.rejected_multiple += 1,
+            Solution::TooHard => result.rejected_too_hard += 1,
+            Solution::None => unreachable!("clues taken from a board are always solvable"),
+        }
+    }
+    result
+}
+
+pub fn generate_pack(options: &PackOptions) -> (Vec<PuzzleCode>, PackStats) {
+    let start = Instant::now();
+    let generate = || -> Vec<TaskResult> {
+        (0..options.count)
+            .into_par_iter()
+            .map(|index| generate_one(options.size, options.seed, index))
+            .collect()
+    };
+    let results = match options.threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("failed to start the generator's threads")
+            .install(generate),
+        None => generate(),
+    };
+
+    let mut stats = PackStats::default();
+    let mut codes = Vec::with_capacity(results.len());
+    for result in results {
+        stats.add(&result);
+        codes.extend(result.code);
+    }
+    stats.elapsed = start.elapsed();
+    (codes, stats)
+}
+
+// Reads `--generate [count] [--size n] [--seed hex] [--threads n]` from the
+// command line
+pub fn options_from_args() -> Option<PackOptions> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--generate")?;
+    let value = |name: &str| {
+        let at = args.iter().position(|arg| arg == name)?;
+        args.get(at + 1)
+    };
+    Some(PackOptions {
+        count: args
+            .get(flag + 1)
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(DEFAULT_PACK_SIZE),
+        size: value("--size")
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(crate::DEFAULT_PUZZLE_SIZE)
+            .clamp(1, MAX_PUZZLE_SIZE),
+        seed: value("--seed")
+            .and_then(|seed| u64::from_str_radix(seed, 16).ok())
+            .unwrap_or_else(rand::random),
+        threads: value("--threads").and_then(|threads| threads.parse().ok()),
+    })
+}
+
+// Prints one puzzle code per line, with statistics on stderr so the codes can
+// be piped into a file
+pub fn run(options: &PackOptions) {
+    let (codes, stats) = generate_pack(options);
+    for code in &codes {
+        println!("{}", code);
+    }
+
+    let seconds = stats.elapsed.as_secs_f64().max(f64::EPSILON);
+    let rejected = stats.rejected_multiple + stats.rejected_too_hard;
+    eprintln!(
+        "{0} {1}x{1} puzzles from seed {2:x} in {3:.2} s ({4:.1} puzzles/s, {5:.1} boards/s)",
+        stats.generated,
+        options.size,
+        options.seed,
+        seconds,
+        stats.generated as f64 / seconds,
+        stats.boards_tried as f64 / seconds,
+    );
+    eprintln!(
+        "{} boards tried, {} rejected ({:.1}%): {} with several solutions, {} too hard to prove unique",
+        stats.boards_tried,
+        rejected,
+        100. * rejected as f64 / stats.boards_tried.max(1) as f64,
+        stats.rejected_multiple,
+        stats.rejected_too_hard,
+    );
+    if stats.failed > 0 {
+        eprintln!(
+            "{} puzzles gave up after {} boards",
+            stats.failed, MAX_ATTEMPTS
+        );
+    }
+}
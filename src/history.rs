@@ -0,0 +1,39 @@
+// Undo and redo stacks. Each step is a group of edits that are undone and
+// redone together, such as committing a whole tentative layer.
+pub struct History<E> {
+    undo: Vec<Vec<E>>,
+    redo: Vec<Vec<E>>,
+}
+
+impl<E> Default for History<E> {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl<E> History<E> {
+    // Records a new step, which makes anything undone so far unrecoverable
+    pub fn push(&mut self, step: Vec<E>) {
+        if !step.is_empty() {
+            self.undo.push(step);
+            self.redo.clear();
+        }
+    }
+
+    // Gives the latest step, which the caller must revert
+    pub fn undo(&mut self) -> Option<&[E]> {
+        let step = self.undo.pop()?;
+        self.redo.push(step);
+        self.redo.last().map(Vec::as_slice)
+    }
+
+    // Gives the latest undone step, which the caller must apply again
+    pub fn redo(&mut self) -> Option<&[E]> {
+        let step = self.redo.pop()?;
+        self.undo.push(step);
+        self.undo.last().map(Vec::as_slice)
+    }
+}
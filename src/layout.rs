@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+
+// Space kept free around the board and HUD
+const MARGIN: f32 = 20.;
+
+// Clue text is sized relative to the cells, within these limits
+const MAX_CLUE_FONT_SIZE: f32 = 16.;
+const MIN_CLUE_FONT_SIZE: f32 = 7.;
+pub const CLUE_CELL_RATIO: f32 = 0.6;
+// Column clues must fit inside this fraction of a cell's width
+const COL_CLUE_FILL: f32 = 0.9;
+// Fira Mono advances 0.6em per character
+pub const MONO_CHAR_WIDTH: f32 = 0.6;
+pub const LINE_HEIGHT: f32 = 1.2;
+
+pub const HUD_FONT_SIZE: f32 = 14.;
+const HUD_CHAR_WIDTH: f32 = HUD_FONT_SIZE * MONO_CHAR_WIDTH;
+const HUD_LINE_HEIGHT: f32 = HUD_FONT_SIZE * LINE_HEIGHT;
+
+fn digits(run: usize) -> usize {
+    run.to_string().len()
+}
+
+// How much room the clues of a puzzle need, in characters of the clue font
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ClueExtents {
+    // Widest row clue list, with one space between numbers
+    pub row_chars: usize,
+    // Most numbers in a column clue list
+    pub col_lines: usize,
+    // Most digits in a single column clue number
+    pub col_digits: usize,
+}
+
+impl ClueExtents {
+    pub fn measure(row_runs: &[Vec<usize>], col_runs: &[Vec<usize>]) -> Self {
+        let row_chars = row_runs
+            .iter()
+            .map(|runs| runs.iter().map(|&run| digits(run) + 1).sum::<usize>() - 1)
+            .max()
+            .unwrap_or(0);
+        let col_lines = col_runs.iter().map(Vec::len).max().unwrap_or(0);
+        let col_digits = col_runs
+            .iter()
+            .flatten()
+            .map(|&run| digits(run))
+            .max()
+            .unwrap_or(1);
+        Self {
+            row_chars,
+            col_lines,
+            col_digits,
+        }
+    }
+}
+
+// Distance of each of a row's clues from the grid, in characters, measured to
+// the clue's centre and ordered outwards from the grid
+pub fn row_clue_offsets(runs: &[usize]) -> Vec<f32> {
+    let mut used = 0;
+    runs.iter()
+        .rev()
+        .map(|&run| {
+            let width = digits(run);
+            let offset = used as f32 + width as f32 / 2.;
+            used += width + 1;
+            offset
+        })
+        .collect()
+}
+
+// Where the board sits in the world, derived from the window size and the
+// measured clues. The main camera maps one world unit to one logical pixel at
+// the default zoom.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoardLayout {
+    pub size: usize,
+    pub grid_size: f32,
+    pub center: Vec2,
+    pub clue_font_size: f32,
+    // Characters per line of HUD text
+    pub hud_columns: usize,
+}
+
+impl BoardLayout {
+    // `hud_len` is the length of the text shown below the board, and
+    // `clue_scale` enlarges the clues beyond their usual size for the grid
+    pub fn new(
+        window_size: Vec2,
+        size: usize,
+        clues: ClueExtents,
+        hud_len: usize,
+        clue_scale: f32,
+    ) -> Self {
+        let hud_columns = (((window_size.x - 2. * MARGIN) / HUD_CHAR_WIDTH) as usize).max(1);
+        let hud_lines = hud_len.div_ceil(hud_columns).max(1);
+        let hud_height = hud_lines as f32 * HUD_LINE_HEIGHT;
+
+        // Clue space per unit of font size; half a character of padding
+        // separates the clues from the grid
+        let row_clue_em = (clues.row_chars as f32 + 1.) * MONO_CHAR_WIDTH;
+        let col_clue_em = clues.col_lines as f32 * LINE_HEIGHT + MONO_CHAR_WIDTH;
+        let available = window_size - Vec2::new(2. * MARGIN, 3. * MARGIN + hud_height);
+
+        // Font size as a fraction of the cell size, shrunk so the widest
+        // column clue still fits its column
+        let ratio = (CLUE_CELL_RATIO * clue_scale)
+            .min(COL_CLUE_FILL / (clues.col_digits as f32 * MONO_CHAR_WIDTH));
+
+        // With the font proportional to the grid, solve for the largest grid
+        // that fits, then clamp the font and fit the grid around that
+        let per_grid = ratio / size as f32;
+        let proportional = (available.x / (1. + row_clue_em * per_grid))
+            .min(available.y / (1. + col_clue_em * per_grid));
+        let clue_font_size = (proportional * per_grid).clamp(
+            MIN_CLUE_FONT_SIZE * clue_scale,
+            MAX_CLUE_FONT_SIZE * clue_scale,
+        );
+        let row_clue_width = row_clue_em * clue_font_size;
+        let col_clue_height = col_clue_em * clue_font_size;
+        let grid_size = (available.x - row_clue_width)
+            .min(available.y - col_clue_height)
+            .max(size as f32);
+
+        // Centre the grid and its clues together in the space above the HUD
+        let area_center = Vec2::new(0., (hud_height + MARGIN) / 2.);
+        let center = area_center + Vec2::new(row_clue_width, -col_clue_height) / 2.;
+
+        Self {
+            size,
+            grid_size,
+            center,
+            clue_font_size,
+            hud_columns,
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.grid_size / self.size as f32
+    }
+
+    // World position of the centre of a cell
+    pub fn cell_center(&self, row: usize, col: usize) -> Vec2 {
+        let corner = self.center - Vec2::splat(self.grid_size / 2.);
+        let cell_size = self.cell_size();
+        corner
+            + Vec2::new(
+                (col as f32 + 0.5) * cell_size,
+                ((self.size - row - 1) as f32 + 0.5) * cell_size,
+            )
+    }
+
+    // Distance from the left (or bottom) edge of the grid to gridline `i`,
+    // which separates cell `i` from cell `i + 1`
+    pub fn line_offset(&self, i: usize) -> f32 {
+        self.cell_size() * (i + 1) as f32
+    }
+
+    fn clue_char_width(&self) -> f32 {
+        self.clue_font_size * MONO_CHAR_WIDTH
+    }
+
+    // Position of a row clue whose centre is `offset` characters from the grid
+    pub fn row_clue_position(&self, row: usize, offset: f32) -> Vec2 {
+        let char_width = self.clue_char_width();
+        Vec2::new(
+            self.center.x - self.grid_size / 2. - char_width * (offset + 0.5),
+            self.cell_center(row, 0).y,
+        )
+    }
+
+    // Position of the `index`th clue of a column, counting outwards from the grid
+    pub fn col_clue_position(&self, col: usize, index: usize) -> Vec2 {
+        let line_height = self.clue_font_size * LINE_HEIGHT;
+        Vec2::new(
+            self.cell_center(0, col).x,
+            self.center.y
+                + self.grid_size / 2.
+                + self.clue_char_width() / 2.
+                + line_height * (index as f32 + 0.5),
+        )
+    }
+
+    // If possible, gives the row and col that contains a world pos
+    pub fn point_coords(&self, pos: Vec2) -> Option<(usize, usize)> {
+        let adjusted = pos - (self.center - Vec2::splat(self.grid_size / 2.));
+        if adjusted.x < 0. || adjusted.y < 0. {
+            return None;
+        }
+        let row_from_bottom = (adjusted.y / self.cell_size()) as usize;
+        let col = (adjusted.x / self.cell_size()) as usize;
+        if row_from_bottom < self.size && col < self.size {
+            Some((self.size - row_from_bottom - 1, col))
+        } else {
+            None
+        }
+    }
+}
+
+// Splits a code into fixed-width lines, since codes have no spaces to wrap at
+pub fn wrap_code(code: &str, width: usize) -> String {
+    code.chars()
+        .collect::<Vec<_>>()
+        .chunks(width)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
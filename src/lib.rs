@@ -0,0 +1,125 @@
+use bevy::app::PluginGroupBuilder;
+use bevy::prelude::*;
+
+pub mod benchmark;
+pub mod board;
+mod board_texture;
+pub mod book;
+mod camera;
+mod clipboard;
+pub mod code;
+pub mod diagnostics;
+pub mod export;
+mod game;
+pub mod generator;
+mod history;
+mod layout;
+mod menu;
+pub mod model;
+mod pause;
+pub mod puzzle_file;
+pub mod replay;
+mod settings;
+pub mod solver;
+mod sound;
+pub mod text;
+mod theme;
+pub mod tui;
+mod win;
+
+pub use code::PuzzleCode;
+pub use game::{CellType, Game, NonogramPlugin, Puzzle};
+pub use menu::{MenuItem, MenuPlugin, PuzzleProblems};
+pub use pause::{PauseItem, PausePlugin};
+pub use puzzle_file::{LoadedPuzzle, PuzzleFile, PuzzleFileError};
+pub use replay::{Replay, ReplayError, ReplayPlayback};
+pub use settings::{
+    MistakeMode, Settings, SettingsError, SettingsFile, SettingsItem, SettingsPlugin,
+};
+pub use sound::{Channel, Mixer, PlaySound, Sound, SoundCategory, SoundPlugin};
+pub use theme::{Theme, ThemeColor, ThemePlugin};
+pub use win::{RevealPicture, WinScreenPlugin};
+
+pub const DEFAULT_PUZZLE_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AppState {
+    MainMenu,
+    EnterCode,
+    Settings,
+    InGame,
+    // Pushed over `InGame`, which stays underneath until resumed
+    Paused,
+    // Pushed over `InGame` while the finished board is animated, then
+    // replaced by the win menu, which is shown over the board
+    Solved,
+    WinMenu,
+    // Says why a puzzle couldn't be played, from `PuzzleProblems`
+    PuzzleError,
+}
+
+// The 2D camera the board is drawn with, which clicks are mapped through.
+// Apps embedding the board add this to their own camera.
+#[derive(Component)]
+pub struct MainCamera;
+
+// Where the puzzle comes from when a game starts without one being picked
+// in the menus
+#[derive(Clone, Debug)]
+pub enum PuzzleSource {
+    Random,
+    Code(PuzzleCode),
+    // A checked puzzle file, revealed in its palette's colors if it has one
+    File(Box<LoadedPuzzle>),
+}
+
+// How the game is set up. Insert it before adding the plugins to change the
+// defaults, e.g. to start straight in a game when embedded in another app.
+#[derive(Clone, Debug)]
+pub struct NonogramConfig {
+    pub initial_state: AppState,
+    pub puzzle: PuzzleSource,
+    // Size of random puzzles, from the config or the main menu
+    pub board_size: usize,
+    pub theme: Theme,
+}
+
+impl Default for NonogramConfig {
+    fn default() -> Self {
+        Self {
+            initial_state: AppState::MainMenu,
+            puzzle: PuzzleSource::Random,
+            board_size: DEFAULT_PUZZLE_SIZE,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl NonogramConfig {
+    pub fn puzzle_code(&self) -> PuzzleCode {
+        match &self.puzzle {
+            PuzzleSource::Random => PuzzleCode::random(self.board_size),
+            PuzzleSource::Code(code) => code.clone(),
+            PuzzleSource::File(puzzle) => puzzle.code(),
+        }
+    }
+}
+
+// The whole game: the board, themes, the menus, settings, pausing, the win
+// screen and sound. The other plugins rely on the state and settings
+// `NonogramPlugin` adds, so embedding apps that only want the board can add
+// `NonogramPlugin` alone, drawn in the config's theme.
+pub struct NonogramPlugins;
+
+impl PluginGroup for NonogramPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(NonogramPlugin)
+            .add(ThemePlugin)
+            .add(MenuPlugin)
+            .add(SettingsPlugin)
+            .add(PausePlugin)
+            .add(WinScreenPlugin)
+            .add(SoundPlugin);
+    }
+}
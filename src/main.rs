@@ -1,677 +1,147 @@
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use bevy::window::WindowResizeConstraints;
+
+use rust_nonogram::benchmark::{self, Benchmark};
+use rust_nonogram::{book, export, generator, puzzle_file, replay, tui};
+use rust_nonogram::{
+    AppState, MainCamera, NonogramConfig, NonogramPlugins, PuzzleCode, PuzzleFile, PuzzleProblems,
+    PuzzleSource, Replay, ReplayPlayback, Settings, SettingsFile,
+};
 
 const WINDOW_WIDTH: f32 = 600.;
 const WINDOW_HEIGHT: f32 = WINDOW_WIDTH;
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum AppState {
-    MainMenu,
-    InGame,
-    WinMenu,
-}
-
-#[derive(Component)]
-struct MainCamera;
+const MIN_WINDOW_SIZE: f32 = 300.;
 
 fn main() {
-    App::new()
-        .insert_resource(WindowDescriptor {
-            width: WINDOW_WIDTH,
-            height: WINDOW_HEIGHT,
-            title: String::from("Rust Nonogram"),
-            vsync: true,
-            resizable: false,
-            ..Default::default()
-        })
-        .add_state(AppState::MainMenu)
-        .add_plugins(DefaultPlugins)
-        .insert_resource(ClearColor(Color::rgb(0.08, 0.10, 0.32)))
-        .add_startup_system(setup)
-        .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(setup_menu))
-        .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(handle_ui_buttons))
-        .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(close_menu))
-        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(setup_game))
-        .add_system_set(SystemSet::on_update(AppState::InGame).with_system(handle_mouse_clicks))
-        .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(close_game))
-        .add_system_set(SystemSet::on_enter(AppState::WinMenu).with_system(setup_win_menu))
-        .add_system_set(SystemSet::on_update(AppState::WinMenu).with_system(handle_win_ui_buttons))
-        .add_system_set(SystemSet::on_exit(AppState::WinMenu).with_system(close_win_menu))
-        .run();
-}
-
-fn setup(mut commands: Commands) {
-    commands
-        .spawn_bundle(OrthographicCameraBundle::new_2d())
-        .insert(MainCamera);
-
-    commands.spawn_bundle(UiCameraBundle::default());
-}
-
-#[derive(Component)]
-struct MainMenu;
-
-#[derive(Component)]
-enum MenuItem {
-    Play,
-}
-
-fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    let text_style = TextStyle {
-        font: font.clone(),
-        font_size: 40.0,
-        color: Color::WHITE,
-    };
-    let text_alignment = TextAlignment {
-        vertical: VerticalAlign::Center,
-        horizontal: HorizontalAlign::Center,
-    };
-
-    commands
-        .spawn_bundle(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                flex_direction: FlexDirection::ColumnReverse,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::SpaceEvenly,
-                ..Default::default()
-            },
-            color: Color::NONE.into(),
-            ..Default::default()
-        })
-        .insert(MainMenu)
-        .with_children(|parent| {
-            parent.spawn_bundle(TextBundle {
-                text: Text::with_section("NONOGRAM", text_style.clone(), text_alignment),
-                ..Default::default()
-            });
-
-            parent
-                .spawn_bundle(ButtonBundle {
-                    style: Style {
-                        size: Size {
-                            width: Val::Percent(10.0),
-                            height: Val::Px(30.0),
-                        },
-                        flex_direction: FlexDirection::ColumnReverse,
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::SpaceEvenly,
-                        ..Style::default()
-                    },
-                    ..ButtonBundle::default()
-                })
-                .insert(MenuItem::Play)
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        style: Style::default(),
-                        text: Text::with_section(
-                            "PLAY",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 20.0,
-                                color: Color::DARK_GRAY,
-                            },
-                            TextAlignment {
-                                vertical: VerticalAlign::Center,
-                                horizontal: HorizontalAlign::Center,
-                            },
-                        ),
-                        ..TextBundle::default()
-                    });
-                });
-        });
-}
-
-fn handle_ui_buttons(
-    mut app_state: ResMut<State<AppState>>,
-    mut mouse_input: ResMut<Input<MouseButton>>,
-    query: Query<(&Interaction, &MenuItem)>,
-) {
-    query.for_each(|(interaction, item)| match interaction {
-        Interaction::Clicked => match item {
-            MenuItem::Play => {
-                app_state.set(AppState::InGame).unwrap();
-                mouse_input.reset(MouseButton::Left);
-            }
-        },
-        Interaction::Hovered => {}
-        _ => {}
-    });
-}
-
-fn close_menu(mut commands: Commands, query: Query<Entity, With<MainMenu>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn_recursive();
+    if let Some(options) = generator::options_from_args() {
+        generator::run(&options);
+        return;
     }
-}
-
-#[derive(Component)]
-struct Game;
-
-struct Solution(Vec<Vec<bool>>);
-
-fn setup_game(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let mut rng = thread_rng();
-    let size = 10;
-    let solution = (0..size)
-        .map(|_| (0..size).map(|_| rng.gen_bool(0.5)).collect())
-        .collect();
-    let puzzle = Puzzle::new(&mut commands, &asset_server, solution);
-    commands.insert_resource(puzzle);
-}
-
-fn close_game(mut commands: Commands, query: Query<Entity, With<Game>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn_recursive();
-    }
-    commands.remove_resource::<Puzzle>();
-}
-
-fn count_runs(line: Vec<bool>) -> Vec<usize> {
-    let mut runs = Vec::new();
-    let mut curr_run = 0;
-    for cell in line {
-        if cell {
-            curr_run += 1;
-        } else if curr_run > 0 {
-            runs.push(curr_run);
-            curr_run = 0;
-        }
-    }
-
-    if curr_run > 0 {
-        runs.push(curr_run);
+    if let Some(args) = export::options_from_args() {
+        export::run(&args);
+        return;
     }
-
-    if runs.len() == 0 {
-        runs.push(0);
+    if let Some(args) = book::options_from_args() {
+        book::run(&args);
+        return;
     }
-
-    runs
-}
-
-struct Puzzle {
-    pub grid: Grid,
-    solution: Vec<Vec<bool>>,
-}
-
-impl Puzzle {
-    pub fn new(
-        commands: &mut Commands,
-        asset_server: &Res<AssetServer>,
-        solution: Vec<Vec<bool>>,
-    ) -> Self {
-        let size = solution.len();
-        assert!(
-            solution.iter().all(|row| row.len() == size),
-            "solution must be square"
-        );
-
-        let grid = Grid::new(commands, asset_server.load("textures/cross.png"), size);
-        let cell_size = grid.cell_size();
-
-        let row_runs: Vec<Vec<usize>> = solution
-            .iter()
-            .map(|row| count_runs(row.to_vec()))
-            .collect();
-        let col_runs: Vec<Vec<usize>> = {
-            let transpose = (0..size).map(|i| (0..size).map(|j| solution[j][i]).collect());
-            transpose.map(|row| count_runs(row)).collect()
-        };
-
-        let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-        let text_style = TextStyle {
-            font,
-            font_size: 16.0,
-            color: Color::WHITE,
-        };
-        let text_alignment = TextAlignment {
-            vertical: VerticalAlign::Center,
-            horizontal: HorizontalAlign::Center,
-        };
-
-        for i in 0..size {
-            for (j, run) in row_runs[size - i - 1].iter().rev().enumerate() {
-                commands
-                    .spawn_bundle(Text2dBundle {
-                        text: Text::with_section(
-                            run.to_string(),
-                            text_style.clone(),
-                            text_alignment,
-                        ),
-                        transform: Transform {
-                            translation: Vec3::new(
-                                -GRID_SIZE / 2. - 15. * (j + 1) as f32,
-                                -(GRID_SIZE - cell_size) / 2. + cell_size * i as f32,
-                                10.,
-                            ),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    })
-                    .insert(Game);
-            }
-
-            for (j, run) in col_runs[i].iter().rev().enumerate() {
-                commands
-                    .spawn_bundle(Text2dBundle {
-                        text: Text::with_section(
-                            run.to_string(),
-                            text_style.clone(),
-                            text_alignment,
-                        ),
-                        transform: Transform {
-                            translation: Vec3::new(
-                                -(GRID_SIZE - cell_size) / 2. + cell_size * i as f32,
-                                GRID_SIZE / 2. + 15. * (j + 1) as f32,
-                                10.,
-                            ),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    })
-                    .insert(Game);
-            }
+    if let Some(paths) = puzzle_file::check_paths_from_args() {
+        if !puzzle_file::run_check(&paths) {
+            std::process::exit(1);
         }
-
-        Self { grid, solution }
+        return;
     }
-
-    pub fn is_solved(&self) -> bool {
-        *self.grid.get_cells() == self.solution
-    }
-
-    pub fn get_solution(&self) -> &Vec<Vec<bool>> {
-        &self.solution
-    }
-}
-
-const GRID_SIZE: f32 = 300.;
-
-#[derive(Copy, Clone, PartialEq)]
-enum CellType {
-    Filled,
-    Cross,
-}
-
-#[derive(Component, Copy, Clone)]
-struct Cell(CellType);
-
-#[derive(Copy, Clone)]
-struct CellEntity {
-    pub cell_type: CellType,
-    pub entity: Entity,
-}
-
-struct Grid {
-    size: usize,
-    entities: Vec<Vec<Option<CellEntity>>>,
-    cells: Vec<Vec<bool>>,
-
-    cross_handle: Handle<Image>,
-}
-
-impl Grid {
-    pub fn new(commands: &mut Commands, cross_handle: Handle<Image>, size: usize) -> Self {
-        let entities = vec![vec![None; size]; size];
-        let cells = vec![vec![false; size]; size];
-
-        let grid_thickness = 0.5;
-
-        // Background
-        commands
-            .spawn_bundle(SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(1.0, 1.0, 1.0),
-                    ..Default::default()
-                },
-                transform: Transform {
-                    translation: Vec3::new(-grid_thickness / 2., -grid_thickness / 2., 0.),
-                    scale: Vec3::new(GRID_SIZE - grid_thickness, GRID_SIZE - grid_thickness, 0.),
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .insert(Game);
-
-        // Grid
-        let grid_padding = GRID_SIZE / size as f32;
-        for i in 0..size - 1 {
-            let sprite = Sprite {
-                color: Color::rgb(0.08, 0.10, 0.62),
-                ..Default::default()
-            };
-            let offset = grid_padding * (i + 1) as f32 - (GRID_SIZE / 2.);
-
-            let thickness = grid_thickness * if (i + 1) % 5 == 0 { 3. } else { 1. };
-
-            commands
-                .spawn_bundle(SpriteBundle {
-                    sprite: sprite.clone(),
-                    transform: Transform {
-                        translation: Vec3::new(offset - (thickness / 2.), 0., 10.),
-                        scale: Vec3::new(thickness, GRID_SIZE, 0.),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(Game);
-            commands
-                .spawn_bundle(SpriteBundle {
-                    sprite: sprite.clone(),
-                    transform: Transform {
-                        translation: Vec3::new(0., offset - (thickness / 2.), 10.),
-                        scale: Vec3::new(GRID_SIZE, thickness, 0.),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(Game);
-        }
-
-        Self {
-            size,
-            entities,
-            cells,
-            cross_handle,
+    if let Some(args) = tui::options_from_args() {
+        if let Err(err) = tui::run(&args) {
+            eprintln!("Terminal error: {}", err);
         }
+        return;
     }
 
-    pub fn set_at(
-        &mut self,
-        commands: &mut Commands,
-        row: usize,
-        col: usize,
-        cell_type: Option<CellType>,
-    ) {
-        self.cells[row][col] = matches!(cell_type, Some(CellType::Filled));
-
-        if let Some(cell_type) = cell_type {
-            self.spawn_at(commands, row, col, cell_type);
-        } else {
-            self.despawn_at(commands, row, col);
-        }
-    }
+    let settings_path = Settings::default_path();
+    let settings = settings_path
+        .as_deref()
+        .map(Settings::load_or_default)
+        .unwrap_or_default();
 
-    // If the cell contains cell_type, set it to empty, else set it to cell_type
-    pub fn toggle_at(
-        &mut self,
-        commands: &mut Commands,
-        row: usize,
-        col: usize,
-        cell_type: CellType,
-    ) {
-        let new_type = if let Some(entity) = self.entities[row][col] {
-            if entity.cell_type == cell_type {
-                None
-            } else {
-                Some(cell_type)
+    let benchmark = Benchmark::from_args();
+    let mut config = NonogramConfig {
+        board_size: settings.board_size,
+        theme: settings.theme(),
+        ..Default::default()
+    };
+    let mut problems = None;
+    if let Some(path) = puzzle_file::path_from_args() {
+        match PuzzleFile::load(&path) {
+            Ok(puzzle) => {
+                config.initial_state = AppState::InGame;
+                config.puzzle = PuzzleSource::File(Box::new(puzzle));
             }
-        } else {
-            Some(cell_type)
-        };
-        self.set_at(commands, row, col, new_type);
-    }
-
-    pub fn get_cells(&self) -> &Vec<Vec<bool>> {
-        &self.cells
-    }
-
-    fn spawn_at(&mut self, commands: &mut Commands, row: usize, col: usize, cell_type: CellType) {
-        self.despawn_at(commands, row, col);
-
-        let grid_thickness = 0.5;
-        let x_pos = col as f32 * self.cell_size();
-        let y_pos = (self.size - row - 1) as f32 * self.cell_size();
-        let mut bundle = SpriteBundle {
-            sprite: Sprite {
-                color: Color::rgb(0.1, 0.1, 0.1),
-                custom_size: Some(Vec2::new(
-                    self.cell_size() - grid_thickness,
-                    self.cell_size() - grid_thickness,
-                )),
-                ..Default::default()
-            },
-            transform: Transform {
-                translation: Vec3::new(
-                    x_pos - grid_thickness / 2.,
-                    y_pos - grid_thickness / 2.,
-                    1.,
-                ) + self.grid_offset(),
-                ..Default::default()
-            },
-            ..Default::default()
-        };
-        match cell_type {
-            CellType::Filled => {}
-            CellType::Cross => {
-                bundle.texture = self.cross_handle.clone();
+            Err(err) => {
+                eprintln!("Can't play {}: {}", path.display(), err);
+                config.initial_state = AppState::PuzzleError;
+                problems = Some(PuzzleProblems {
+                    source: path.display().to_string(),
+                    problems: err.problems(),
+                });
             }
         }
-        let entity_id = commands
-            .spawn_bundle(bundle)
-            .insert(Cell(cell_type))
-            .insert(Game)
-            .id();
-
-        self.entities[row][col] = Some(CellEntity {
-            cell_type,
-            entity: entity_id,
-        });
     }
-
-    fn despawn_at(&mut self, commands: &mut Commands, row: usize, col: usize) {
-        if let Some(cell_entity) = self.entities[row][col] {
-            commands.entity(cell_entity.entity).despawn();
+    let mut playback = None;
+    if let Some(path) = replay::path_from_args() {
+        match Replay::read(&path) {
+            Ok(replay) => {
+                config.initial_state = AppState::InGame;
+                config.puzzle = PuzzleSource::Code(replay.code.clone());
+                playback = Some(ReplayPlayback::new(replay));
+            }
+            Err(err) => {
+                eprintln!("Can't play back {}: {}", path.display(), err);
+                config.initial_state = AppState::PuzzleError;
+                problems = Some(PuzzleProblems {
+                    source: path.display().to_string(),
+                    problems: vec![err.to_string()],
+                });
+            }
         }
-        self.entities[row][col] = None;
     }
-
-    // If possible, gives the row and col that contains a world pos
-    pub fn point_coords(&self, pos: Vec2) -> Option<(usize, usize)> {
-        let offset = pos - self.grid_offset().truncate();
-        let adjusted = offset + Vec2::new(self.cell_size() / 2., self.cell_size() / 2.);
-        if adjusted.x < 0. || adjusted.y < 0. {
-            return None;
-        }
-        let row = self.size - (adjusted.y / self.cell_size()) as usize - 1;
-        let col = (adjusted.x / self.cell_size()) as usize;
-        if row < self.size && col < self.size {
-            Some((row, col))
-        } else {
-            None
-        }
+    if let Some(benchmark) = &benchmark {
+        config.initial_state = AppState::InGame;
+        config.puzzle = PuzzleSource::Code(PuzzleCode::random(benchmark.size));
     }
 
-    fn cell_size(&self) -> f32 {
-        GRID_SIZE / self.size as f32
+    let mut app = App::new();
+    app.insert_resource(WindowDescriptor {
+        width: WINDOW_WIDTH,
+        height: WINDOW_HEIGHT,
+        title: String::from("Rust Nonogram"),
+        vsync: settings.vsync,
+        resize_constraints: WindowResizeConstraints {
+            min_width: MIN_WINDOW_SIZE,
+            min_height: MIN_WINDOW_SIZE,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert_resource(ClearColor(config.theme.background))
+    .insert_resource(config)
+    .insert_resource(settings)
+    .add_plugins(DefaultPlugins)
+    .add_plugins(NonogramPlugins)
+    .add_startup_system(setup);
+
+    if let Some(path) = settings_path {
+        app.insert_resource(SettingsFile(path));
+    }
+    if let Some(problems) = problems {
+        app.insert_resource(problems);
+    }
+    if let Some(playback) = playback {
+        app.insert_resource(playback);
+    }
+
+    if let Some(benchmark) = benchmark {
+        app.insert_resource(benchmark).add_system_set(
+            SystemSet::on_update(AppState::InGame).with_system(
+                benchmark::run_benchmark
+                    .label("edit_board")
+                    .before("sync_cells"),
+            ),
+        );
     }
 
-    // Offset to center grid
-    fn grid_offset(&self) -> Vec3 {
-        let offset = -(GRID_SIZE - self.cell_size()) / 2.0;
-        Vec3::new(offset, offset, 0.0)
-    }
+    app.run();
 }
 
-fn handle_mouse_clicks(
-    mut app_state: ResMut<State<AppState>>,
-    mut commands: Commands,
-    mouse_input: Res<Input<MouseButton>>,
-    windows: Res<Windows>,
-    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-    mut puzzle: ResMut<Puzzle>,
-) {
-    let win = windows.get_primary().expect("no primary window");
-    let (camera, camera_transform) = camera.single();
-
-    let left_clicked = mouse_input.just_pressed(MouseButton::Left);
-    let right_clicked = mouse_input.just_pressed(MouseButton::Right);
-    if left_clicked || right_clicked {
-        if let Some(click_pos) = win.cursor_position() {
-            let window_size = Vec2::new(win.width() as f32, win.height() as f32);
-            let ndc = (click_pos / window_size) * 2.0 - Vec2::ONE;
-            let ndc_to_world =
-                camera_transform.compute_matrix() * camera.projection_matrix.inverse();
-            let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0)).truncate();
-
-            if let Some((row, col)) = puzzle.grid.point_coords(world_pos) {
-                if left_clicked {
-                    puzzle
-                        .grid
-                        .toggle_at(&mut commands, row, col, CellType::Filled);
-                } else if right_clicked {
-                    puzzle
-                        .grid
-                        .toggle_at(&mut commands, row, col, CellType::Cross);
-                }
-            }
-        }
-
-        if puzzle.is_solved() {
-            commands.insert_resource(Solution(puzzle.get_solution().to_vec()));
-            app_state.set(AppState::WinMenu).unwrap();
-        }
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Picks up edits to theme files while the game runs
+    if let Err(err) = asset_server.watch_for_changes() {
+        eprintln!("Not watching assets for changes: {:?}", err);
     }
-}
-
-#[derive(Component)]
-struct WinMenu;
-
-#[derive(Component)]
-enum WinMenuItem {
-    MainMenu,
-}
-
-fn setup_win_menu(mut commands: Commands, asset_server: Res<AssetServer>, solution: Res<Solution>) {
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    let text_style = TextStyle {
-        font: font.clone(),
-        font_size: 40.0,
-        color: Color::WHITE,
-    };
-    let text_alignment = TextAlignment {
-        vertical: VerticalAlign::Center,
-        horizontal: HorizontalAlign::Center,
-    };
 
     commands
-        .spawn_bundle(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                flex_direction: FlexDirection::ColumnReverse,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::SpaceEvenly,
-                ..Default::default()
-            },
-            color: Color::NONE.into(),
-            ..Default::default()
-        })
-        .insert(WinMenu)
-        .with_children(|parent| {
-            parent.spawn_bundle(TextBundle {
-                text: Text::with_section("CORRECT", text_style.clone(), text_alignment),
-                ..Default::default()
-            });
-
-            let size = solution.0.len();
-            let solution_size = 150.;
-            let cell_size = solution_size / size as f32;
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size {
-                            width: Val::Px(solution_size),
-                            height: Val::Px(solution_size),
-                        },
-                        ..Style::default()
-                    },
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    for row in 0..size {
-                        for col in 0..size {
-                            if !solution.0[row][col] {
-                                continue;
-                            }
-                            parent.spawn_bundle(NodeBundle {
-                                style: Style {
-                                    size: Size::new(
-                                        Val::Px(cell_size * 1.05),
-                                        Val::Px(cell_size * 1.05),
-                                    ),
-                                    position_type: PositionType::Absolute,
-                                    position: Rect {
-                                        left: Val::Px(col as f32 * cell_size),
-                                        bottom: Val::Px((size - row - 1) as f32 * cell_size),
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                },
-                                color: Color::rgb(0.1, 0.1, 0.1).into(),
-                                ..Default::default()
-                            });
-                        }
-                    }
-                });
-
-            parent
-                .spawn_bundle(ButtonBundle {
-                    style: Style {
-                        size: Size {
-                            width: Val::Percent(17.0),
-                            height: Val::Px(30.0),
-                        },
-                        flex_direction: FlexDirection::ColumnReverse,
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::SpaceEvenly,
-                        ..Style::default()
-                    },
-                    ..ButtonBundle::default()
-                })
-                .insert(WinMenuItem::MainMenu)
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        style: Style::default(),
-                        text: Text::with_section(
-                            "MAIN MENU",
-                            TextStyle {
-                                font: font.clone(),
-                                font_size: 20.0,
-                                color: Color::DARK_GRAY,
-                            },
-                            TextAlignment {
-                                vertical: VerticalAlign::Center,
-                                horizontal: HorizontalAlign::Center,
-                            },
-                        ),
-                        ..TextBundle::default()
-                    });
-                });
-        });
-}
-
-fn close_win_menu(mut commands: Commands, query: Query<Entity, With<WinMenu>>) {
-    for entity in query.iter() {
-        commands.entity(entity).despawn_recursive();
-    }
-    commands.remove_resource::<Solution>();
-}
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
 
-fn handle_win_ui_buttons(
-    mut app_state: ResMut<State<AppState>>,
-    mut mouse_input: ResMut<Input<MouseButton>>,
-    query: Query<(&Interaction, &WinMenuItem)>,
-) {
-    query.for_each(|(interaction, item)| match interaction {
-        Interaction::Clicked => match item {
-            WinMenuItem::MainMenu => {
-                app_state.set(AppState::MainMenu).unwrap();
-                mouse_input.reset(MouseButton::Left);
-            }
-        },
-        Interaction::Hovered => {}
-        _ => {}
-    });
+    commands.spawn_bundle(UiCameraBundle::default());
 }
@@ -0,0 +1,635 @@
+use bevy::prelude::*;
+
+use crate::clipboard::Clipboard;
+use crate::code::PuzzleCode;
+use crate::game::NextPuzzle;
+use crate::layout::wrap_code;
+use crate::theme::{Theme, ThemeColor};
+use crate::{AppState, NonogramConfig};
+
+// The main menu, the screen for entering a puzzle code, and the screen
+// saying why a puzzle can't be played
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clipboard>()
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(setup_menu))
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(handle_ui_buttons))
+            .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(close_menu))
+            .add_system_set(SystemSet::on_enter(AppState::EnterCode).with_system(setup_code_menu))
+            .add_system_set(
+                SystemSet::on_update(AppState::EnterCode)
+                    .with_system(handle_code_input)
+                    .with_system(update_code_input_text)
+                    .with_system(handle_code_ui_buttons),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::EnterCode).with_system(close_code_menu))
+            .add_system_set(
+                SystemSet::on_enter(AppState::PuzzleError).with_system(setup_error_screen),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::PuzzleError).with_system(handle_error_screen_input),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::PuzzleError).with_system(close_error_screen),
+            );
+    }
+}
+
+#[derive(Component)]
+struct MainMenu;
+
+#[derive(Component, Debug, PartialEq)]
+pub enum MenuItem {
+    Play,
+    EnterCode,
+    Settings,
+}
+
+fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: theme.text,
+    };
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(MainMenu)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("NONOGRAM", text_style.clone(), text_alignment),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Text);
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Percent(10.0),
+                            height: Val::Px(30.0),
+                        },
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceEvenly,
+                        ..Style::default()
+                    },
+                    color: theme.button.into(),
+                    ..ButtonBundle::default()
+                })
+                .insert(ThemeColor::Button)
+                .insert(MenuItem::Play)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style::default(),
+                            text: Text::with_section(
+                                "PLAY",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 20.0,
+                                    color: theme.button_text,
+                                },
+                                TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            ..TextBundle::default()
+                        })
+                        .insert(ThemeColor::ButtonText);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Percent(20.0),
+                            height: Val::Px(30.0),
+                        },
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceEvenly,
+                        ..Style::default()
+                    },
+                    color: theme.button.into(),
+                    ..ButtonBundle::default()
+                })
+                .insert(ThemeColor::Button)
+                .insert(MenuItem::EnterCode)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style::default(),
+                            text: Text::with_section(
+                                "ENTER CODE",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 20.0,
+                                    color: theme.button_text,
+                                },
+                                TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            ..TextBundle::default()
+                        })
+                        .insert(ThemeColor::ButtonText);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Percent(20.0),
+                            height: Val::Px(30.0),
+                        },
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceEvenly,
+                        ..Style::default()
+                    },
+                    color: theme.button.into(),
+                    ..ButtonBundle::default()
+                })
+                .insert(ThemeColor::Button)
+                .insert(MenuItem::Settings)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style::default(),
+                            text: Text::with_section(
+                                "SETTINGS",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 20.0,
+                                    color: theme.button_text,
+                                },
+                                TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            ..TextBundle::default()
+                        })
+                        .insert(ThemeColor::ButtonText);
+                });
+        });
+}
+
+fn handle_ui_buttons(
+    mut commands: Commands,
+    config: Res<NonogramConfig>,
+    mut app_state: ResMut<State<AppState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    query: Query<(&Interaction, &MenuItem)>,
+) {
+    query.for_each(|(interaction, item)| match interaction {
+        Interaction::Clicked => match item {
+            MenuItem::Play => {
+                commands.insert_resource(NextPuzzle(PuzzleCode::random(config.board_size)));
+                app_state.set(AppState::InGame).unwrap();
+                mouse_input.reset(MouseButton::Left);
+            }
+            MenuItem::EnterCode => {
+                app_state.set(AppState::EnterCode).unwrap();
+                mouse_input.reset(MouseButton::Left);
+            }
+            MenuItem::Settings => {
+                app_state.set(AppState::Settings).unwrap();
+                mouse_input.reset(MouseButton::Left);
+            }
+        },
+        Interaction::Hovered => {}
+        _ => {}
+    });
+}
+
+fn close_menu(mut commands: Commands, query: Query<Entity, With<MainMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Component)]
+struct CodeMenu;
+
+#[derive(Component)]
+struct CodeInputText;
+
+#[derive(Component)]
+struct CodeStatusText;
+
+#[derive(Component)]
+enum CodeMenuItem {
+    Play,
+    Back,
+}
+
+// The code being typed or pasted on the code entry screen
+#[derive(Default)]
+struct CodeInput(String);
+
+fn setup_code_menu(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    commands.insert_resource(CodeInput::default());
+
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let mono_font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: theme.text,
+    };
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(CodeMenu)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("ENTER CODE", text_style.clone(), text_alignment),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Text);
+
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "_",
+                        TextStyle {
+                            font: mono_font.clone(),
+                            font_size: 16.0,
+                            color: theme.text,
+                        },
+                        text_alignment,
+                    ),
+                    ..Default::default()
+                })
+                .insert(CodeInputText)
+                .insert(ThemeColor::Text);
+
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "TYPE OR PASTE (CTRL+V) A CODE",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 16.0,
+                            color: theme.hud,
+                        },
+                        text_alignment,
+                    ),
+                    ..Default::default()
+                })
+                .insert(CodeStatusText)
+                .insert(ThemeColor::Hud);
+
+            for (item, label) in [(CodeMenuItem::Play, "PLAY"), (CodeMenuItem::Back, "BACK")] {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size {
+                                width: Val::Percent(10.0),
+                                height: Val::Px(30.0),
+                            },
+                            flex_direction: FlexDirection::ColumnReverse,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceEvenly,
+                            ..Style::default()
+                        },
+                        color: theme.button.into(),
+                        ..ButtonBundle::default()
+                    })
+                    .insert(ThemeColor::Button)
+                    .insert(item)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                style: Style::default(),
+                                text: Text::with_section(
+                                    label,
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 20.0,
+                                        color: theme.button_text,
+                                    },
+                                    TextAlignment {
+                                        vertical: VerticalAlign::Center,
+                                        horizontal: HorizontalAlign::Center,
+                                    },
+                                ),
+                                ..TextBundle::default()
+                            })
+                            .insert(ThemeColor::ButtonText);
+                    });
+            }
+        });
+}
+
+// Tries to start the puzzle described by the entered code, or shows why it can't
+fn submit_code(
+    commands: &mut Commands,
+    app_state: &mut State<AppState>,
+    input: &CodeInput,
+    theme: &Theme,
+    status_query: &mut Query<(&mut Text, &mut ThemeColor), With<CodeStatusText>>,
+) {
+    match input.0.parse::<PuzzleCode>() {
+        Ok(code) => {
+            commands.insert_resource(NextPuzzle(code));
+            app_state.set(AppState::InGame).unwrap();
+        }
+        Err(err) => {
+            let (mut status, mut which) = status_query.single_mut();
+            status.sections[0].value = err.to_string().to_uppercase();
+            status.sections[0].style.color = theme.error;
+            *which = ThemeColor::Error;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_code_input(
+    mut commands: Commands,
+    mut app_state: ResMut<State<AppState>>,
+    mut input: ResMut<CodeInput>,
+    mut clipboard: ResMut<Clipboard>,
+    mut char_events: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    theme: Res<Theme>,
+    mut status_query: Query<(&mut Text, &mut ThemeColor), With<CodeStatusText>>,
+) {
+    let ctrl = keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    for event in char_events.iter() {
+        if !event.char.is_control() && !ctrl {
+            input.0.push(event.char);
+        }
+    }
+
+    if ctrl && keyboard_input.just_pressed(KeyCode::V) {
+        if let Some(text) = clipboard.get_text() {
+            input.0.push_str(text.trim());
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        input.0.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.set(AppState::MainMenu).unwrap();
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        submit_code(
+            &mut commands,
+            &mut app_state,
+            &input,
+            &theme,
+            &mut status_query,
+        );
+    }
+}
+
+fn update_code_input_text(input: Res<CodeInput>, mut query: Query<&mut Text, With<CodeInputText>>) {
+    if input.is_changed() {
+        query.single_mut().sections[0].value = wrap_code(&format!("{}_", input.0), 40);
+    }
+}
+
+fn handle_code_ui_buttons(
+    mut commands: Commands,
+    mut app_state: ResMut<State<AppState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    input: Res<CodeInput>,
+    theme: Res<Theme>,
+    query: Query<(&Interaction, &CodeMenuItem)>,
+    mut status_query: Query<(&mut Text, &mut ThemeColor), With<CodeStatusText>>,
+) {
+    for (interaction, item) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match item {
+            CodeMenuItem::Play => submit_code(
+                &mut commands,
+                &mut app_state,
+                &input,
+                &theme,
+                &mut status_query,
+            ),
+            CodeMenuItem::Back => app_state.set(AppState::MainMenu).unwrap(),
+        }
+        mouse_input.reset(MouseButton::Left);
+    }
+}
+
+fn close_code_menu(mut commands: Commands, query: Query<Entity, With<CodeMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<CodeInput>();
+}
+
+// Why a puzzle can't be played. Insert it before entering
+// `AppState::PuzzleError`, which shows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PuzzleProblems {
+    // Where the puzzle came from, like its file name
+    pub source: String,
+    pub problems: Vec<String>,
+}
+
+// Problems past this many are counted rather than listed
+const MAX_PROBLEMS_SHOWN: usize = 8;
+
+#[derive(Component)]
+struct ErrorScreen;
+
+#[derive(Component)]
+struct ErrorScreenBack;
+
+fn setup_error_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    theme: Res<Theme>,
+    problems: Res<PuzzleProblems>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let mono_font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+    // Long problems wrap to fit the window
+    let width = windows.get_primary().map_or(560., |win| win.width() - 40.);
+    let wrapped = Style {
+        max_size: Size::new(Val::Px(width), Val::Undefined),
+        ..Default::default()
+    };
+
+    let mut lines: Vec<String> = problems
+        .problems
+        .iter()
+        .take(MAX_PROBLEMS_SHOWN)
+        .map(|problem| problem.to_uppercase())
+        .collect();
+    if problems.problems.len() > MAX_PROBLEMS_SHOWN {
+        lines.push(format!(
+            "AND {} MORE",
+            problems.problems.len() - MAX_PROBLEMS_SHOWN
+        ));
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(ErrorScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "CAN'T PLAY THIS PUZZLE",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 32.0,
+                            color: theme.text,
+                        },
+                        text_alignment,
+                    ),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Text);
+
+            parent
+                .spawn_bundle(TextBundle {
+                    style: wrapped.clone(),
+                    text: Text::with_section(
+                        problems.source.clone(),
+                        TextStyle {
+                            font: mono_font.clone(),
+                            font_size: 16.0,
+                            color: theme.hud,
+                        },
+                        text_alignment,
+                    ),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Hud);
+
+            parent
+                .spawn_bundle(TextBundle {
+                    style: wrapped,
+                    text: Text::with_section(
+                        lines.join("\n"),
+                        TextStyle {
+                            font: mono_font,
+                            font_size: 14.0,
+                            color: theme.error,
+                        },
+                        text_alignment,
+                    ),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Error);
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Percent(25.0),
+                            height: Val::Px(30.0),
+                        },
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceEvenly,
+                        ..Style::default()
+                    },
+                    color: theme.button.into(),
+                    ..ButtonBundle::default()
+                })
+                .insert(ThemeColor::Button)
+                .insert(ErrorScreenBack)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style::default(),
+                            text: Text::with_section(
+                                "MAIN MENU",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 20.0,
+                                    color: theme.button_text,
+                                },
+                                text_alignment,
+                            ),
+                            ..TextBundle::default()
+                        })
+                        .insert(ThemeColor::ButtonText);
+                });
+        });
+}
+
+// Goes back to the main menu on clicking the button, or on Escape or Enter
+fn handle_error_screen_input(
+    mut app_state: ResMut<State<AppState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    query: Query<&Interaction, With<ErrorScreenBack>>,
+) {
+    let clicked = query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked);
+    if clicked || keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::Return]) {
+        app_state.set(AppState::MainMenu).unwrap();
+        mouse_input.reset(MouseButton::Left);
+    }
+}
+
+fn close_error_screen(mut commands: Commands, query: Query<Entity, With<ErrorScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<PuzzleProblems>();
+}
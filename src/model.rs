@@ -0,0 +1,442 @@
+use crate::board::{clues, line_matches, BitBoard};
+use crate::history::History;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CellType {
+    Filled,
+    Cross,
+    // A "maybe" mark, for cells the player suspects are filled
+    Dot,
+}
+
+// Who placed a mark: the player, the player while exploring a hypothesis,
+// or auto-cross on their behalf
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Layer {
+    Main,
+    Tentative,
+    Auto,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Mark {
+    pub cell_type: CellType,
+    pub layer: Layer,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BoardLine {
+    Row(usize),
+    Col(usize),
+}
+
+// A change to one cell's mark, whoever made it. The game sends these as
+// events once a frame.
+#[derive(Copy, Clone, Debug)]
+pub struct CellChanged {
+    pub row: usize,
+    pub col: usize,
+    pub mark: Option<Mark>,
+    // The mark it replaced
+    pub before: Option<Mark>,
+}
+
+impl CellChanged {
+    // Whether auto-cross made the change, rather than the player
+    pub fn is_automatic(&self) -> bool {
+        match (self.before, self.mark) {
+            (_, Some(mark)) => mark.layer == Layer::Auto,
+            (Some(before), None) => before.layer == Layer::Auto,
+            (None, None) => false,
+        }
+    }
+}
+
+// Something the player did to the board. Doing the same actions in order to
+// a new board for the same puzzle, with auto-cross set the same way, leaves
+// the same marks. Only toggles that changed the board are kept.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    Toggle {
+        row: usize,
+        col: usize,
+        cell_type: CellType,
+        // Whether the mark went on the tentative layer
+        tentative: bool,
+    },
+    Undo,
+    Redo,
+    Commit,
+    Discard,
+}
+
+// A change the player made to one cell, kept so it can be undone
+#[derive(Copy, Clone)]
+struct CellEdit {
+    row: usize,
+    col: usize,
+    before: Option<Mark>,
+    after: Option<Mark>,
+}
+
+// A puzzle being played: its solution and clues, the marks on the board and
+// the player's history. Nothing here draws anything, so the game and the
+// terminal UI play by the same rules.
+pub struct Board {
+    // Whether the player's marks go on the tentative layer
+    pub tentative: bool,
+    marks: Vec<Vec<Option<Mark>>>,
+    // Filled cells on the main layer, which are what must match the solution
+    cells: BitBoard,
+    // Changes not yet taken by `take_changes`
+    changes: Vec<CellChanged>,
+    // Actions not yet taken by `take_actions`
+    actions: Vec<Action>,
+    history: History<CellEdit>,
+    solution: BitBoard,
+    row_runs: Vec<Vec<usize>>,
+    col_runs: Vec<Vec<usize>>,
+    // Lines whose filled cells currently match their clues
+    complete_rows: Vec<bool>,
+    complete_cols: Vec<bool>,
+}
+
+impl Board {
+    pub fn new(solution: BitBoard) -> Self {
+        let size = solution.size();
+        let (row_runs, col_runs) = clues(&solution);
+        let cells = BitBoard::new(size);
+        // Lines with no filled cells are complete before anything is marked
+        let complete_rows = (0..size)
+            .map(|i| line_matches(cells.row(i), &row_runs[i]))
+            .collect();
+        let complete_cols = (0..size)
+            .map(|i| line_matches(cells.col(i), &col_runs[i]))
+            .collect();
+        Self {
+            tentative: false,
+            marks: vec![vec![None; size]; size],
+            cells,
+            changes: Vec::new(),
+            actions: Vec::new(),
+            history: History::default(),
+            solution,
+            row_runs,
+            col_runs,
+            complete_rows,
+            complete_cols,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.solution.size()
+    }
+
+    pub fn row_runs(&self) -> &[Vec<usize>] {
+        &self.row_runs
+    }
+
+    pub fn col_runs(&self) -> &[Vec<usize>] {
+        &self.col_runs
+    }
+
+    pub fn mark_at(&self, row: usize, col: usize) -> Option<Mark> {
+        self.marks[row][col]
+    }
+
+    pub fn get_cells(&self) -> &BitBoard {
+        &self.cells
+    }
+
+    // Only filled cells on the main layer count towards the solution
+    fn set_mark(&mut self, row: usize, col: usize, mark: Option<Mark>) {
+        if self.marks[row][col] == mark {
+            return;
+        }
+        let filled = mark
+            == Some(Mark {
+                cell_type: CellType::Filled,
+                layer: Layer::Main,
+            });
+        self.cells.set(row, col, filled);
+        let before = std::mem::replace(&mut self.marks[row][col], mark);
+        self.changes.push(CellChanged {
+            row,
+            col,
+            mark,
+            before,
+        });
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    // Every change to the marks since the last call
+    pub fn take_changes(&mut self) -> Vec<CellChanged> {
+        std::mem::take(&mut self.changes)
+    }
+
+    pub fn has_actions(&self) -> bool {
+        !self.actions.is_empty()
+    }
+
+    // Every action the player has taken since the last call, in order
+    pub fn take_actions(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.actions)
+    }
+
+    // Takes an action as if the player had, e.g. when replaying a solve
+    pub fn apply(&mut self, action: Action) {
+        match action {
+            Action::Toggle {
+                row,
+                col,
+                cell_type,
+                tentative,
+            } => {
+                self.tentative = tentative;
+                self.toggle_mark(row, col, cell_type);
+            }
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Commit => self.commit_tentative(),
+            Action::Discard => self.discard_tentative(),
+        }
+    }
+
+    // The positions and types of every mark on the tentative layer
+    pub fn tentative_marks(&self) -> Vec<(usize, usize, CellType)> {
+        let mut marks = Vec::new();
+        for (row, row_marks) in self.marks.iter().enumerate() {
+            for (col, mark) in row_marks.iter().enumerate() {
+                if let Some(mark) = mark {
+                    if mark.layer == Layer::Tentative {
+                        marks.push((row, col, mark.cell_type));
+                    }
+                }
+            }
+        }
+        marks
+    }
+
+    // Checks a line against its clues, giving its new state if it has just
+    // been completed or reopened
+    pub fn update_line(&mut self, line: BoardLine) -> Option<bool> {
+        let (complete, was_complete) = match line {
+            BoardLine::Row(row) => (
+                line_matches(self.cells.row(row), &self.row_runs[row]),
+                &mut self.complete_rows[row],
+            ),
+            BoardLine::Col(col) => (
+                line_matches(self.cells.col(col), &self.col_runs[col]),
+                &mut self.complete_cols[col],
+            ),
+        };
+        if complete == *was_complete {
+            return None;
+        }
+        *was_complete = complete;
+        Some(complete)
+    }
+
+    pub fn is_line_complete(&self, line: BoardLine) -> bool {
+        match line {
+            BoardLine::Row(row) => self.complete_rows[row],
+            BoardLine::Col(col) => self.complete_cols[col],
+        }
+    }
+
+    // Crosses the empty cells of a line that is complete, and clears
+    // automatic crosses from one that is not. Only lines that have just
+    // changed are refreshed, so players can still remove automatic crosses by
+    // hand.
+    pub fn refresh_line_auto_cross(&mut self, line: BoardLine) {
+        for i in 0..self.size() {
+            let (row, col) = match line {
+                BoardLine::Row(row) => (row, i),
+                BoardLine::Col(col) => (i, col),
+            };
+            self.refresh_auto_cross(row, col, true);
+        }
+    }
+
+    // Brings every cell in line with the auto-cross setting, for when it is
+    // switched on or off mid-game
+    pub fn set_auto_cross(&mut self, enabled: bool) {
+        let size = self.size();
+        for row in 0..size {
+            for col in 0..size {
+                self.refresh_auto_cross(row, col, enabled);
+            }
+        }
+    }
+
+    fn refresh_auto_cross(&mut self, row: usize, col: usize, enabled: bool) {
+        let wanted = enabled && (self.complete_rows[row] || self.complete_cols[col]);
+        match self.mark_at(row, col) {
+            None if wanted => {
+                let mark = Mark {
+                    cell_type: CellType::Cross,
+                    layer: Layer::Auto,
+                };
+                self.set_mark(row, col, Some(mark));
+            }
+            Some(mark) if mark.layer == Layer::Auto && !wanted => self.set_mark(row, col, None),
+            _ => {}
+        }
+    }
+
+    // Takes the changes made since the last call, bringing completed lines
+    // and auto-crosses up to date at once. The game does the same over a few
+    // frames with events, which other systems listen to.
+    pub fn settle(&mut self, auto_cross: bool) -> Vec<CellChanged> {
+        let mut settled = Vec::new();
+        loop {
+            let changes = self.take_changes();
+            if changes.is_empty() {
+                return settled;
+            }
+            for change in &changes {
+                for line in [BoardLine::Row(change.row), BoardLine::Col(change.col)] {
+                    if self.update_line(line).is_some() && auto_cross {
+                        self.refresh_line_auto_cross(line);
+                    }
+                }
+            }
+            settled.extend(changes);
+        }
+    }
+
+    // Places the player's mark on the current layer, or clears it if the
+    // same mark is already there. Each layer only changes its own marks and
+    // empty cells; auto-crosses count as empty on the main layer.
+    pub fn toggle_mark(&mut self, row: usize, col: usize, cell_type: CellType) {
+        let layer = if self.tentative {
+            Layer::Tentative
+        } else {
+            Layer::Main
+        };
+        let current = self.mark_at(row, col);
+        let editable = match current {
+            None => true,
+            Some(mark) => {
+                mark.layer == layer || (mark.layer == Layer::Auto && layer == Layer::Main)
+            }
+        };
+        if !editable {
+            return;
+        }
+        self.actions.push(Action::Toggle {
+            row,
+            col,
+            cell_type,
+            tentative: self.tentative,
+        });
+
+        let mark = Mark { cell_type, layer };
+        let new_mark = if current == Some(mark) {
+            None
+        } else {
+            Some(mark)
+        };
+        self.edit(vec![(row, col, new_mark)]);
+    }
+
+    // Moves every tentative mark onto the main layer, as one undoable step
+    pub fn commit_tentative(&mut self) {
+        let edits = self
+            .tentative_marks()
+            .into_iter()
+            .map(|(row, col, cell_type)| {
+                let mark = Mark {
+                    cell_type,
+                    layer: Layer::Main,
+                };
+                (row, col, Some(mark))
+            })
+            .collect();
+        self.edit(edits);
+        self.tentative = false;
+        self.actions.push(Action::Commit);
+    }
+
+    // Clears every tentative mark, as one undoable step
+    pub fn discard_tentative(&mut self) {
+        let edits = self
+            .tentative_marks()
+            .into_iter()
+            .map(|(row, col, _)| (row, col, None))
+            .collect();
+        self.edit(edits);
+        self.tentative = false;
+        self.actions.push(Action::Discard);
+    }
+
+    // Applies a group of the player's edits and records them as one step
+    fn edit(&mut self, edits: Vec<(usize, usize, Option<Mark>)>) {
+        let step: Vec<CellEdit> = edits
+            .into_iter()
+            .map(|(row, col, after)| CellEdit {
+                row,
+                col,
+                before: self.player_mark(row, col),
+                after,
+            })
+            .filter(|edit| edit.before != edit.after)
+            .collect();
+        for edit in &step {
+            self.set_mark(edit.row, edit.col, edit.after);
+        }
+        self.history.push(step);
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(step) = self.history.undo().map(<[CellEdit]>::to_vec) {
+            for edit in step.iter().rev() {
+                self.set_mark(edit.row, edit.col, edit.before);
+            }
+            self.actions.push(Action::Undo);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(step) = self.history.redo().map(<[CellEdit]>::to_vec) {
+            for edit in &step {
+                self.set_mark(edit.row, edit.col, edit.after);
+            }
+            self.actions.push(Action::Redo);
+        }
+    }
+
+    // The mark the player placed in a cell, ignoring auto-crosses
+    fn player_mark(&self, row: usize, col: usize) -> Option<Mark> {
+        self.mark_at(row, col)
+            .filter(|mark| mark.layer != Layer::Auto)
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.cells == self.solution
+    }
+
+    pub fn get_solution(&self) -> &BitBoard {
+        &self.solution
+    }
+
+    // Whether the player's mark contradicts the solution: a fill where it is
+    // empty, or a cross where it is filled. Maybe dots, tentative marks and
+    // auto-crosses are never mistakes.
+    pub fn is_mistake(&self, row: usize, col: usize, mark: Option<Mark>) -> bool {
+        match mark {
+            Some(Mark {
+                cell_type,
+                layer: Layer::Main,
+            }) => match cell_type {
+                CellType::Filled => !self.solution.get(row, col),
+                CellType::Cross => self.solution.get(row, col),
+                CellType::Dot => false,
+            },
+            _ => false,
+        }
+    }
+}
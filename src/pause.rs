@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+
+use crate::code::PuzzleCode;
+use crate::game::{Game, NextPuzzle, Puzzle};
+use crate::theme::{Theme, ThemeColor};
+use crate::win::Solution;
+use crate::AppState;
+
+// Escape pauses a game, hiding the board and stopping the timer, and offers
+// ways to carry on or leave it
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::InGame).with_system(handle_pause_key))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Paused)
+                    .with_system(setup_pause_menu)
+                    .with_system(hide_board),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Paused)
+                    .with_system(handle_resume_key)
+                    .with_system(handle_pause_ui_buttons),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Paused)
+                    .with_system(close_pause_menu)
+                    .with_system(show_board),
+            );
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub enum PauseItem {
+    Resume,
+    // Starts the same puzzle again from an empty board
+    Restart,
+    Reveal,
+    NewPuzzle,
+    MainMenu,
+}
+
+// Board entities hidden while paused, so that cells which were already
+// hidden stay that way on resuming
+#[derive(Component)]
+struct HiddenByPause;
+
+fn handle_pause_key(
+    mut app_state: ResMut<State<AppState>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.push(AppState::Paused).unwrap();
+        keyboard_input.reset(KeyCode::Escape);
+    }
+}
+
+fn handle_resume_key(
+    mut app_state: ResMut<State<AppState>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.pop().unwrap();
+        keyboard_input.reset(KeyCode::Escape);
+    }
+}
+
+fn hide_board(mut commands: Commands, mut query: Query<(Entity, &mut Visibility), With<Game>>) {
+    for (entity, mut visibility) in query.iter_mut() {
+        if visibility.is_visible {
+            visibility.is_visible = false;
+            commands.entity(entity).insert(HiddenByPause);
+        }
+    }
+}
+
+fn show_board(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Visibility), With<HiddenByPause>>,
+) {
+    for (entity, mut visibility) in query.iter_mut() {
+        visibility.is_visible = true;
+        commands.entity(entity).remove::<HiddenByPause>();
+    }
+}
+
+fn setup_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>, theme: Res<Theme>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: theme.text,
+    };
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(PauseMenu)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("PAUSED", text_style.clone(), text_alignment),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Text);
+
+            let items = [
+                (PauseItem::Resume, "RESUME"),
+                (PauseItem::Restart, "RESTART"),
+                (PauseItem::Reveal, "REVEAL SOLUTION"),
+                (PauseItem::NewPuzzle, "NEW PUZZLE"),
+                (PauseItem::MainMenu, "MAIN MENU"),
+            ];
+            for (item, label) in items {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size {
+                                width: Val::Percent(25.0),
+                                height: Val::Px(30.0),
+                            },
+                            flex_direction: FlexDirection::ColumnReverse,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceEvenly,
+                            ..Style::default()
+                        },
+                        color: theme.button.into(),
+                        ..ButtonBundle::default()
+                    })
+                    .insert(ThemeColor::Button)
+                    .insert(item)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                style: Style::default(),
+                                text: Text::with_section(
+                                    label,
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 20.0,
+                                        color: theme.button_text,
+                                    },
+                                    TextAlignment {
+                                        vertical: VerticalAlign::Center,
+                                        horizontal: HorizontalAlign::Center,
+                                    },
+                                ),
+                                ..TextBundle::default()
+                            })
+                            .insert(ThemeColor::ButtonText);
+                    });
+            }
+        });
+}
+
+// Every option but Resume leaves the game, replacing the whole state stack
+// so that `InGame` is exited and `close_game` tears the board down. Restart
+// and New Puzzle then enter `InGame` afresh.
+fn handle_pause_ui_buttons(
+    mut commands: Commands,
+    mut app_state: ResMut<State<AppState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    code: Res<PuzzleCode>,
+    puzzle: Res<Puzzle>,
+    query: Query<(&Interaction, &PauseItem)>,
+) {
+    for (interaction, item) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match item {
+            PauseItem::Resume => app_state.pop().unwrap(),
+            PauseItem::Restart => {
+                commands.insert_resource(NextPuzzle(code.clone()));
+                app_state.replace(AppState::InGame).unwrap();
+            }
+            PauseItem::Reveal => {
+                commands.insert_resource(Solution { revealed: true });
+                app_state.set(AppState::Solved).unwrap();
+            }
+            PauseItem::NewPuzzle => {
+                let size = puzzle.board.get_solution().size();
+                commands.insert_resource(NextPuzzle(PuzzleCode::random(size)));
+                app_state.replace(AppState::InGame).unwrap();
+            }
+            PauseItem::MainMenu => app_state.replace(AppState::MainMenu).unwrap(),
+        }
+        mouse_input.reset(MouseButton::Left);
+        return;
+    }
+}
+
+fn close_pause_menu(mut commands: Commands, query: Query<Entity, With<PauseMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::Color;
+use ron::extensions::Extensions;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{clues, BitBoard};
+use crate::book::Difficulty;
+use crate::code::PuzzleCode;
+use crate::diagnostics::{self, line_name, ClueProblem};
+use crate::model::BoardLine;
+use crate::theme::hex_color;
+use crate::win::RevealPicture;
+
+// A puzzle as written in a `.json` or `.ron` file: its clues, and
+// optionally the picture they describe and who made it. The fields are
+// described in `assets/puzzle.schema.json`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PuzzleFile {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    pub size: usize,
+    // Clues from the top row down and the left column across. Empty lines
+    // have the clue `[0]`, or `[]`.
+    pub rows: Vec<Vec<usize>>,
+    pub columns: Vec<Vec<usize>>,
+    // One string per row, with `.` for empty cells and `#` or a palette key
+    // for filled ones. Without it, the clues must have just one solution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<Vec<String>>,
+    // The color of each key in the solution, which its cells turn to once
+    // the puzzle is solved
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub palette: BTreeMap<char, PaletteColor>,
+    // Rated from the clues when it isn't given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PaletteColor(#[serde(with = "hex_color")] pub Color);
+
+// The cell character that is never filled, and the one filled cells use
+// when there is no palette
+const EMPTY_KEY: char = '.';
+const FILLED_KEY: char = '#';
+
+#[derive(Debug)]
+pub enum PuzzleFileError {
+    Io(io::Error),
+    UnknownFormat,
+    Json(serde_json::Error),
+    Ron(ron::Error),
+    // Every problem found with the clues
+    Clues(Vec<ClueProblem>),
+    ReservedKey(char),
+    PaletteWithoutSolution,
+    SolutionRows {
+        found: usize,
+        size: usize,
+    },
+    SolutionRowLength {
+        row: usize,
+        found: usize,
+        size: usize,
+    },
+    UnknownKey {
+        row: usize,
+        col: usize,
+        key: char,
+    },
+    // Every line whose clue the solution doesn't match
+    SolutionMismatch(Vec<BoardLine>),
+}
+
+impl PuzzleFileError {
+    // What is wrong, one problem to a line
+    pub fn problems(&self) -> Vec<String> {
+        match self {
+            PuzzleFileError::Clues(problems) => problems.iter().map(ToString::to_string).collect(),
+            PuzzleFileError::SolutionMismatch(lines) => {
+                lines.iter().map(|line| mismatch_message(*line)).collect()
+            }
+            err => vec![err.to_string()],
+        }
+    }
+}
+
+impl fmt::Display for PuzzleFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PuzzleFileError::Io(err) => write!(f, "{}", err),
+            PuzzleFileError::UnknownFormat => write!(f, "puzzle files must end in .json or .ron"),
+            PuzzleFileError::Json(err) => write!(f, "invalid JSON: {}", err),
+            PuzzleFileError::Ron(err) => write!(f, "invalid RON: {}", err),
+            PuzzleFileError::Clues(problems) => {
+                let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+                write!(f, "{}", problems.join("; "))
+            }
+            PuzzleFileError::ReservedKey(key) => {
+                write!(f, "`{}` marks empty cells and can't be in the palette", key)
+            }
+            PuzzleFileError::PaletteWithoutSolution => {
+                write!(f, "a palette needs a solution to color")
+            }
+            PuzzleFileError::SolutionRows { found, size } => {
+                write!(f, "the solution has {} rows, not {}", found, size)
+            }
+            PuzzleFileError::SolutionRowLength { row, found, size } => write!(
+                f,
+                "solution row {} has {} cells, not {}",
+                row + 1,
+                found,
+                size
+            ),
+            PuzzleFileError::UnknownKey { row, col, key } => write!(
+                f,
+                "solution row {}, column {} is `{}`, which isn't in the palette",
+                row + 1,
+                col + 1,
+                key
+            ),
+            PuzzleFileError::SolutionMismatch(lines) => {
+                let problems: Vec<String> =
+                    lines.iter().map(|line| mismatch_message(*line)).collect();
+                write!(f, "{}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PuzzleFileError {}
+
+fn mismatch_message(line: BoardLine) -> String {
+    format!("the solution doesn't match {}'s clue", line_name(line))
+}
+
+// A puzzle file that passed every check, with its solution worked out
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedPuzzle {
+    pub file: PuzzleFile,
+    pub solution: BitBoard,
+    // Colored from the palette, if the file has one
+    pub picture: Option<RevealPicture>,
+}
+
+impl LoadedPuzzle {
+    pub fn code(&self) -> PuzzleCode {
+        PuzzleCode::Custom(self.solution.clone())
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.file
+            .difficulty
+            .unwrap_or_else(|| Difficulty::rate(&self.solution))
+    }
+}
+
+impl PuzzleFile {
+    // A file describing a solution, with its clues
+    pub fn from_solution(title: &str, solution: &BitBoard) -> Self {
+        let size = solution.size();
+        let (rows, columns) = clues(solution);
+        let cells = (0..size)
+            .map(|row| {
+                solution
+                    .row(row)
+                    .iter()
+                    .map(|filled| if filled { FILLED_KEY } else { EMPTY_KEY })
+                    .collect()
+            })
+            .collect();
+        Self {
+            title: title.to_string(),
+            author: None,
+            license: None,
+            size,
+            rows,
+            columns,
+            solution: Some(cells),
+            palette: BTreeMap::new(),
+            difficulty: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, PuzzleFileError> {
+        serde_json::from_str(text).map_err(PuzzleFileError::Json)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, PuzzleFileError> {
+        ron::from_str(text).map_err(PuzzleFileError::Ron)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("puzzle files are always valid JSON")
+    }
+
+    // Written with `implicit_some`, so optional fields need no `Some(...)`
+    pub fn to_ron(&self) -> String {
+        let config = ron::ser::PrettyConfig::new()
+            .depth_limit(2)
+            .extensions(Extensions::IMPLICIT_SOME);
+        ron::ser::to_string_pretty(self, config).expect("puzzle files are always valid RON")
+    }
+
+    // Reads a file in the format its extension names, without checking it
+    pub fn read(path: &Path) -> Result<Self, PuzzleFileError> {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        if !matches!(extension, Some("json") | Some("ron")) {
+            return Err(PuzzleFileError::UnknownFormat);
+        }
+        let text = fs::read_to_string(path).map_err(PuzzleFileError::Io)?;
+        if extension == Some("json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_ron(&text)
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<LoadedPuzzle, PuzzleFileError> {
+        Self::read(path)?.validate()
+    }
+
+    // Checks that the clues describe a board of the given size, and that
+    // the solution matches them. Without a solution, the clues are solved
+    // for one.
+    pub fn validate(self) -> Result<LoadedPuzzle, PuzzleFileError> {
+        let size = self.size;
+        let rows = normalize(&self.rows);
+        let columns = normalize(&self.columns);
+        let problems = diagnostics::check_clues(size, &rows, &columns);
+        if !problems.is_empty() {
+            return Err(PuzzleFileError::Clues(problems));
+        }
+        if self.palette.contains_key(&EMPTY_KEY) {
+            return Err(PuzzleFileError::ReservedKey(EMPTY_KEY));
+        }
+
+        let (solution, picture) = match &self.solution {
+            Some(cells) => {
+                let (solution, picture) = self.read_solution(cells)?;
+                let (solution_rows, solution_columns) = clues(&solution);
+                let mismatched_rows = (0..size)
+                    .filter(|&row| solution_rows[row] != rows[row])
+                    .map(BoardLine::Row);
+                let mismatched_columns = (0..size)
+                    .filter(|&col| solution_columns[col] != columns[col])
+                    .map(BoardLine::Col);
+                let mismatched: Vec<BoardLine> =
+                    mismatched_rows.chain(mismatched_columns).collect();
+                if !mismatched.is_empty() {
+                    return Err(PuzzleFileError::SolutionMismatch(mismatched));
+                }
+                (solution, picture)
+            }
+            None if !self.palette.is_empty() => {
+                return Err(PuzzleFileError::PaletteWithoutSolution);
+            }
+            None => {
+                let solution = diagnostics::solve_clues(&rows, &columns)
+                    .map_err(|problem| PuzzleFileError::Clues(vec![problem]))?;
+                (solution, None)
+            }
+        };
+        Ok(LoadedPuzzle {
+            file: self,
+            solution,
+            picture,
+        })
+    }
+
+    // The solution's filled cells, and its picture if there is a palette
+    fn read_solution(
+        &self,
+        cells: &[String],
+    ) -> Result<(BitBoard, Option<RevealPicture>), PuzzleFileError> {
+        let size = self.size;
+        if cells.len() != size {
+            let found = cells.len();
+            return Err(PuzzleFileError::SolutionRows { found, size });
+        }
+        let mut solution = BitBoard::new(size);
+        let mut colors = Vec::with_capacity(size * size);
+        for (row, text) in cells.iter().enumerate() {
+            let keys: Vec<char> = text.chars().collect();
+            if keys.len() != size {
+                let found = keys.len();
+                return Err(PuzzleFileError::SolutionRowLength { row, found, size });
+            }
+            for (col, key) in keys.into_iter().enumerate() {
+                let color = self.palette.get(&key).map(|color| color.0);
+                let known = key == EMPTY_KEY
+                    || color.is_some()
+                    || (key == FILLED_KEY && self.palette.is_empty());
+                if !known {
+                    return Err(PuzzleFileError::UnknownKey { row, col, key });
+                }
+                solution.set(row, col, key != EMPTY_KEY);
+                colors.push(color.unwrap_or(Color::NONE));
+            }
+        }
+        let picture = (!self.palette.is_empty()).then(|| RevealPicture::new(size, colors));
+        Ok((solution, picture))
+    }
+}
+
+// Empty lines as the game writes them, with the clue 0
+fn normalize(clues: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    clues
+        .iter()
+        .map(|clue| {
+            if clue.is_empty() {
+                vec![0]
+            } else {
+                clue.clone()
+            }
+        })
+        .collect()
+}
+
+// The file to play from `--puzzle <file>`
+pub fn path_from_args() -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--puzzle")?;
+    args.get(flag + 1).map(PathBuf::from)
+}
+
+// The files to check from `--check <file>...`
+pub fn check_paths_from_args() -> Option<Vec<PathBuf>> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--check")?;
+    Some(args[flag + 1..].iter().map(PathBuf::from).collect())
+}
+
+// Checks each file, printing everything wrong with it. Returns whether
+// they were all valid.
+pub fn run_check(paths: &[PathBuf]) -> bool {
+    let mut valid = true;
+    for path in paths {
+        match PuzzleFile::load(path) {
+            Ok(puzzle) => println!(
+                "{}: {}x{} {}, ok",
+                path.display(),
+                puzzle.file.size,
+                puzzle.file.size,
+                puzzle.difficulty().name().to_lowercase()
+            ),
+            Err(err) => {
+                println!("{}:", path.display());
+                for problem in err.problems() {
+                    println!("  {}", problem);
+                }
+                valid = false;
+            }
+        }
+    }
+    valid
+}
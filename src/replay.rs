@@ -0,0 +1,381 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+
+use crate::code::{ParseCodeError, PuzzleCode};
+use crate::game::{GameTimer, Puzzle};
+use crate::model::{Action, CellType};
+
+// The first line of every replay file, with the format's version
+const HEADER: &str = "nonogram-replay";
+const VERSION: u32 = 1;
+
+// Playback runs between these speeds, doubling or halving with `]` and `[`
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 16.;
+
+// Something the player did, and when, on the game's timer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Step {
+    pub time: Duration,
+    pub action: Action,
+}
+
+// Everything the player did in one game, in order. Playing the steps back on
+// a new board for the code re-enacts the solve.
+//
+// As text, a replay is a header, the puzzle code and then one step to a line:
+// the milliseconds since the last step, then what was done. Cells are toggled
+// with `f` (fill), `x` (cross) or `d` (dot) and the cell's 0-based
+// `row,col`, in capitals on the tentative layer:
+//
+//     nonogram-replay 1
+//     code 10-5eed
+//     1250 f 0,3
+//     400 X 2,2
+//     900 undo
+//     80 commit
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub code: PuzzleCode,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    MissingHeader,
+    UnsupportedVersion(String),
+    MissingCode,
+    InvalidCode(ParseCodeError),
+    // A step that can't be read, by its line number from 1
+    InvalidStep { line: usize, text: String },
+    OutsideBoard { line: usize, row: usize, col: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::MissingHeader => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "replay version {} is not supported", version)
+            }
+            ReplayError::MissingCode => write!(f, "line 2 should be the puzzle code"),
+            ReplayError::InvalidCode(err) => write!(f, "invalid puzzle code: {}", err),
+            ReplayError::InvalidStep { line, text } => {
+                write!(f, "line {}: `{}` is not a step", line, text)
+            }
+            ReplayError::OutsideBoard { line, row, col } => write!(
+                f,
+                "line {}: row {}, column {} is outside the board",
+                line,
+                row + 1,
+                col + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn new(code: PuzzleCode) -> Self {
+        Self {
+            code,
+            steps: Vec::new(),
+        }
+    }
+
+    // Adds actions taken at the same time
+    pub fn record(&mut self, time: Duration, actions: impl IntoIterator<Item = Action>) {
+        let steps = actions.into_iter().map(|action| Step { time, action });
+        self.steps.extend(steps);
+    }
+
+    // When the last step was taken
+    pub fn duration(&self) -> Duration {
+        self.steps.last().map_or(Duration::ZERO, |step| step.time)
+    }
+
+    // Times are kept to the millisecond
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\ncode {}\n", HEADER, VERSION, self.code);
+        let mut last = 0;
+        for step in &self.steps {
+            let time = step.time.as_millis();
+            text += &format!(
+                "{} {}\n",
+                time.saturating_sub(last),
+                action_text(step.action)
+            );
+            last = time;
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, ReplayError> {
+        let mut lines = text.lines().enumerate();
+        let header = lines.next().map(|(_, line)| line.trim());
+        let version = header
+            .and_then(|header| header.strip_prefix(HEADER))
+            .ok_or(ReplayError::MissingHeader)?
+            .trim();
+        if version != VERSION.to_string() {
+            return Err(ReplayError::UnsupportedVersion(version.to_string()));
+        }
+        let code = lines
+            .next()
+            .and_then(|(_, line)| line.trim().strip_prefix("code "))
+            .ok_or(ReplayError::MissingCode)?;
+        let code: PuzzleCode = code.trim().parse().map_err(ReplayError::InvalidCode)?;
+
+        let size = code.size();
+        let mut replay = Self::new(code);
+        let mut time = Duration::ZERO;
+        for (index, text) in lines {
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            let line = index + 1;
+            let invalid = || ReplayError::InvalidStep {
+                line,
+                text: text.to_string(),
+            };
+            let (delta, action) = text.split_once(' ').ok_or_else(invalid)?;
+            let delta: u64 = delta.parse().map_err(|_| invalid())?;
+            let action = parse_action(action.trim()).ok_or_else(invalid)?;
+            if let Action::Toggle { row, col, .. } = action {
+                if row >= size || col >= size {
+                    return Err(ReplayError::OutsideBoard { line, row, col });
+                }
+            }
+            time += Duration::from_millis(delta);
+            replay.steps.push(Step { time, action });
+        }
+        Ok(replay)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        Self::from_text(&text)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+fn action_text(action: Action) -> String {
+    match action {
+        Action::Toggle {
+            row,
+            col,
+            cell_type,
+            tentative,
+        } => {
+            let op = match cell_type {
+                CellType::Filled => 'f',
+                CellType::Cross => 'x',
+                CellType::Dot => 'd',
+            };
+            let op = if tentative {
+                op.to_ascii_uppercase()
+            } else {
+                op
+            };
+            format!("{} {},{}", op, row, col)
+        }
+        Action::Undo => "undo".to_string(),
+        Action::Redo => "redo".to_string(),
+        Action::Commit => "commit".to_string(),
+        Action::Discard => "discard".to_string(),
+    }
+}
+
+fn parse_action(text: &str) -> Option<Action> {
+    match text {
+        "undo" => return Some(Action::Undo),
+        "redo" => return Some(Action::Redo),
+        "commit" => return Some(Action::Commit),
+        "discard" => return Some(Action::Discard),
+        _ => {}
+    }
+    let (op, cell) = text.split_once(' ')?;
+    let mut chars = op.chars();
+    let op = chars.next().filter(|_| chars.next().is_none())?;
+    let cell_type = match op.to_ascii_lowercase() {
+        'f' => CellType::Filled,
+        'x' => CellType::Cross,
+        'd' => CellType::Dot,
+        _ => return None,
+    };
+    let (row, col) = cell.trim().split_once(',')?;
+    Some(Action::Toggle {
+        row: row.trim().parse().ok()?,
+        col: col.trim().parse().ok()?,
+        cell_type,
+        tentative: op.is_ascii_uppercase(),
+    })
+}
+
+// Writes a replay to the documents folder (or the working directory), giving
+// where it went
+pub(crate) fn save(replay: &Replay) -> io::Result<PathBuf> {
+    let dir = dirs::document_dir().unwrap_or_else(|| ".".into());
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = dir.join(format!("nonogram-{}.replay", stamp));
+    replay.write(&path)?;
+    Ok(path)
+}
+
+// The file to play back from `--replay <file>`
+pub fn path_from_args() -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--replay")?;
+    args.get(flag + 1).map(PathBuf::from)
+}
+
+// A replay being played back on the current game, which takes the place of
+// the player's input until it ends. Insert it before the game starts, with
+// the replay's code as the puzzle.
+pub struct ReplayPlayback {
+    replay: Replay,
+    // The first step not yet taken
+    next: usize,
+    // How far into the replay playback is, which runs at `speed` times the
+    // real time
+    clock: Duration,
+    speed: f32,
+    paused: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next: 0,
+            clock: Duration::ZERO,
+            speed: 1.,
+            paused: false,
+        }
+    }
+
+    pub fn clock(&self) -> Duration {
+        self.clock
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+}
+
+// Takes the replay's steps as their time comes. `]` and `[` speed playback up
+// and slow it down, and Space pauses it. Once every step is taken, the
+// player carries on from there, with the replay so far kept as theirs.
+pub(crate) fn play_back(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    mut puzzle: ResMut<Puzzle>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+    let playback = &mut *playback;
+    if keyboard_input.just_pressed(KeyCode::RBracket) {
+        let speed = playback.speed * 2.;
+        playback.set_speed(speed);
+    }
+    if keyboard_input.just_pressed(KeyCode::LBracket) {
+        let speed = playback.speed / 2.;
+        playback.set_speed(speed);
+    }
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if !playback.paused {
+        playback.clock += time.delta().mul_f32(playback.speed);
+    }
+
+    while let Some(&step) = playback.replay.steps.get(playback.next) {
+        if step.time > playback.clock {
+            break;
+        }
+        puzzle.board.apply(step.action);
+        playback.next += 1;
+    }
+    if playback.next == playback.replay.steps.len() {
+        commands.insert_resource(playback.replay.clone());
+        commands.remove_resource::<ReplayPlayback>();
+    }
+}
+
+// Stamps the player's actions with the game's time
+pub(crate) fn record_actions(
+    timer: Res<GameTimer>,
+    playback: Option<Res<ReplayPlayback>>,
+    mut replay: ResMut<Replay>,
+    mut puzzle: ResMut<Puzzle>,
+) {
+    if !puzzle.board.has_actions() {
+        return;
+    }
+    let actions = puzzle.board.take_actions();
+    // Played back steps are already in the replay being played
+    if playback.is_none() {
+        replay.record(timer.0.elapsed(), actions);
+    }
+}
+
+// `R` saves the replay of the game so far
+pub(crate) fn handle_save_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    replay: Res<Replay>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::R) {
+        return;
+    }
+    let replay = playback
+        .as_ref()
+        .map_or(&*replay, |playback| &playback.replay);
+    match save(replay) {
+        Ok(path) => eprintln!("Saved the replay to {}", path.display()),
+        Err(err) => eprintln!("Couldn't save the replay: {}", err),
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct PlaybackText;
+
+pub(crate) fn update_playback_text(
+    playback: Option<Res<ReplayPlayback>>,
+    mut query: Query<&mut Text, With<PlaybackText>>,
+) {
+    let value = match &playback {
+        Some(playback) if playback.paused => {
+            format!("REPLAY {}X PAUSED - SPACE TO RESUME", playback.speed)
+        }
+        Some(playback) => format!("REPLAY {}X - [ ] TO CHANGE SPEED", playback.speed),
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
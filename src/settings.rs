@@ -0,0 +1,482 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::window::WindowResized;
+use serde::{Deserialize, Serialize};
+
+use crate::code::MAX_PUZZLE_SIZE;
+use crate::theme::{Theme, ThemeColor};
+use crate::{AppState, NonogramConfig, DEFAULT_PUZZLE_SIZE};
+
+// The settings menu fits windows this tall at full size, and shrinks to fit
+// shorter ones
+const MENU_HEIGHT: f32 = 450.;
+const TITLE_FONT_SIZE: f32 = 40.;
+const BUTTON_HEIGHT: f32 = 30.;
+const BUTTON_FONT_SIZE: f32 = 20.;
+
+// The player's preferences, kept in a TOML file between runs. Fields missing
+// from the file take their default values.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Left click crosses and right click fills, instead of the other way round
+    pub swap_mouse_buttons: bool,
+    pub auto_cross: bool,
+    pub mistakes: MistakeMode,
+    // One of `Theme::NAMES`, or the name of a theme file in `assets/themes`
+    pub theme: String,
+    // From 0 to 1. `volume` scales everything, then the others scale
+    // sound effects and music.
+    pub volume: f32,
+    pub effects_volume: f32,
+    pub music_volume: f32,
+    // Silences everything without forgetting the volumes
+    pub muted: bool,
+    // Size of random puzzles started from the main menu
+    pub board_size: usize,
+    pub vsync: bool,
+    // Enlarges clue numbers, from 1 (their usual size for the grid) to 2
+    pub clue_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            swap_mouse_buttons: false,
+            auto_cross: false,
+            mistakes: MistakeMode::Off,
+            theme: Theme::NAMES[0].to_string(),
+            volume: 0.8,
+            effects_volume: 1.,
+            music_volume: 0.5,
+            muted: false,
+            board_size: DEFAULT_PUZZLE_SIZE,
+            vsync: true,
+            clue_scale: 1.,
+        }
+    }
+}
+
+// Whether marks that contradict the solution are pointed out as they are made
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MistakeMode {
+    Off,
+    Highlight,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(err) => write!(f, "{}", err),
+            SettingsError::Parse(err) => write!(f, "invalid settings file: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl Settings {
+    // Where settings are kept, in the user's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust-nonogram").join("settings.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SettingsError> {
+        let text = fs::read_to_string(path).map_err(SettingsError::Io)?;
+        let mut settings: Self = toml::from_str(&text).map_err(SettingsError::Parse)?;
+        settings.board_size = settings.board_size.clamp(1, MAX_PUZZLE_SIZE);
+        settings.volume = settings.volume.clamp(0., 1.);
+        settings.effects_volume = settings.effects_volume.clamp(0., 1.);
+        settings.music_volume = settings.music_volume.clamp(0., 1.);
+        settings.clue_scale = settings.clue_scale.clamp(1., 2.);
+        Ok(settings)
+    }
+
+    // Loads the settings if there are any, falling back to the defaults if
+    // the file is missing or broken
+    pub fn load_or_default(path: &Path) -> Self {
+        match Self::load(path) {
+            Ok(settings) => settings,
+            Err(SettingsError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                eprintln!("Ignoring settings in {}: {}", path.display(), err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).expect("settings are always valid TOML");
+        fs::write(path, text)
+    }
+
+    pub fn theme(&self) -> Theme {
+        Theme::named(&self.theme).unwrap_or_default()
+    }
+
+    // Custom themes stay in the theme cycle, after the built-in ones, once
+    // `custom_theme` has seen them
+    fn cycle(&mut self, item: SettingsItem, custom_theme: &mut Option<String>) {
+        match item {
+            SettingsItem::SwapMouseButtons => self.swap_mouse_buttons = !self.swap_mouse_buttons,
+            SettingsItem::AutoCross => self.auto_cross = !self.auto_cross,
+            SettingsItem::Mistakes => {
+                self.mistakes = match self.mistakes {
+                    MistakeMode::Off => MistakeMode::Highlight,
+                    MistakeMode::Highlight => MistakeMode::Off,
+                }
+            }
+            SettingsItem::Theme => {
+                if !Theme::NAMES.contains(&self.theme.as_str()) {
+                    *custom_theme = Some(self.theme.clone());
+                }
+                let names: Vec<&str> = Theme::NAMES
+                    .iter()
+                    .copied()
+                    .chain(custom_theme.as_deref())
+                    .collect();
+                let current = names.iter().position(|name| *name == self.theme);
+                let next = current.map_or(0, |i| (i + 1) % names.len());
+                self.theme = names[next].to_string();
+            }
+            SettingsItem::Volume => self.volume = next_volume(self.volume),
+            SettingsItem::EffectsVolume => self.effects_volume = next_volume(self.effects_volume),
+            SettingsItem::MusicVolume => self.music_volume = next_volume(self.music_volume),
+            SettingsItem::Mute => self.muted = !self.muted,
+            SettingsItem::BoardSize => {
+                let next = BOARD_SIZES.iter().find(|&&size| size > self.board_size);
+                self.board_size = *next.unwrap_or(&BOARD_SIZES[0]);
+            }
+            SettingsItem::Vsync => self.vsync = !self.vsync,
+            SettingsItem::ClueScale => {
+                let next = CLUE_SCALES.iter().find(|&&scale| scale > self.clue_scale);
+                self.clue_scale = *next.unwrap_or(&CLUE_SCALES[0]);
+            }
+            SettingsItem::Back => {}
+        }
+    }
+
+    fn label(&self, item: SettingsItem) -> String {
+        let on_off = |on| if on { "ON" } else { "OFF" };
+        match item {
+            SettingsItem::SwapMouseButtons => {
+                if self.swap_mouse_buttons {
+                    "MOUSE: LEFT CROSSES".to_string()
+                } else {
+                    "MOUSE: LEFT FILLS".to_string()
+                }
+            }
+            SettingsItem::AutoCross => format!("AUTO-CROSS: {}", on_off(self.auto_cross)),
+            SettingsItem::Mistakes => match self.mistakes {
+                MistakeMode::Off => "MISTAKES: HIDDEN".to_string(),
+                MistakeMode::Highlight => "MISTAKES: HIGHLIGHTED".to_string(),
+            },
+            SettingsItem::Theme => format!("THEME: {}", self.theme.to_uppercase()),
+            SettingsItem::Volume => format!("VOLUME: {}%", (self.volume * 100.).round()),
+            SettingsItem::EffectsVolume => {
+                format!("EFFECTS: {}%", (self.effects_volume * 100.).round())
+            }
+            SettingsItem::MusicVolume => format!("MUSIC: {}%", (self.music_volume * 100.).round()),
+            SettingsItem::Mute => {
+                if self.muted {
+                    "SOUND: MUTED".to_string()
+                } else {
+                    "SOUND: ON".to_string()
+                }
+            }
+            SettingsItem::BoardSize => {
+                format!("PUZZLE SIZE: {}x{}", self.board_size, self.board_size)
+            }
+            SettingsItem::Vsync => format!("VSYNC: {}", on_off(self.vsync)),
+            SettingsItem::ClueScale => format!("CLUE SIZE: {}%", (self.clue_scale * 100.).round()),
+            SettingsItem::Back => "BACK".to_string(),
+        }
+    }
+}
+
+// Volumes step up by a tenth, wrapping round from full to silent
+fn next_volume(volume: f32) -> f32 {
+    let step = (volume * 10.).round() as u32;
+    ((step + 1) % 11) as f32 / 10.
+}
+
+// Sizes the settings screen cycles through
+const BOARD_SIZES: [usize; 10] = [5, 10, 15, 20, 25, 30, 40, 50, 75, 100];
+const CLUE_SCALES: [f32; 4] = [1., 1.25, 1.5, 2.];
+
+// Where changed settings are saved. Without it, as when embedded or in
+// tests, changes only last until the app closes.
+pub struct SettingsFile(pub PathBuf);
+
+// The settings screen, reached from the main menu, and applying changed
+// settings wherever they are changed from
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_settings)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Settings).with_system(setup_settings_menu),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Settings)
+                    .with_system(handle_settings_ui_buttons)
+                    .with_system(handle_settings_back_key)
+                    .with_system(update_settings_labels)
+                    .with_system(scale_settings_menu),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::Settings).with_system(close_settings_menu),
+            );
+    }
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct SettingsTitle;
+
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub enum SettingsItem {
+    SwapMouseButtons,
+    AutoCross,
+    Mistakes,
+    Theme,
+    Volume,
+    EffectsVolume,
+    MusicVolume,
+    Mute,
+    BoardSize,
+    Vsync,
+    ClueScale,
+    Back,
+}
+
+// Carries changed settings over to the config and window, and saves them.
+// `ThemePlugin` follows the theme setting, and `SoundPlugin` the volumes.
+fn apply_settings(
+    settings: Res<Settings>,
+    file: Option<Res<SettingsFile>>,
+    mut config: ResMut<NonogramConfig>,
+    mut windows: ResMut<Windows>,
+) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    config.board_size = settings.board_size;
+    if let Some(window) = windows.get_primary_mut() {
+        if window.vsync() != settings.vsync {
+            window.set_vsync(settings.vsync);
+        }
+    }
+
+    if let Some(file) = file {
+        if let Err(err) = settings.save(&file.0) {
+            eprintln!("Couldn't save settings to {}: {}", file.0.display(), err);
+        }
+    }
+}
+
+fn setup_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+) {
+    let scale = menu_scale(&windows);
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: TITLE_FONT_SIZE * scale,
+        color: theme.text,
+    };
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(SettingsMenu)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("SETTINGS", text_style.clone(), text_alignment),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Text)
+                .insert(SettingsTitle);
+
+            let items = [
+                SettingsItem::SwapMouseButtons,
+                SettingsItem::AutoCross,
+                SettingsItem::Mistakes,
+                SettingsItem::Theme,
+                SettingsItem::Volume,
+                SettingsItem::EffectsVolume,
+                SettingsItem::MusicVolume,
+                SettingsItem::Mute,
+                SettingsItem::BoardSize,
+                SettingsItem::ClueScale,
+                SettingsItem::Vsync,
+                SettingsItem::Back,
+            ];
+            for item in items {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size {
+                                width: Val::Percent(40.0),
+                                height: Val::Px(BUTTON_HEIGHT * scale),
+                            },
+                            flex_direction: FlexDirection::ColumnReverse,
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceEvenly,
+                            ..Style::default()
+                        },
+                        color: theme.button.into(),
+                        ..ButtonBundle::default()
+                    })
+                    .insert(ThemeColor::Button)
+                    .insert(item)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                style: Style::default(),
+                                text: Text::with_section(
+                                    settings.label(item),
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: BUTTON_FONT_SIZE * scale,
+                                        color: theme.button_text,
+                                    },
+                                    TextAlignment {
+                                        vertical: VerticalAlign::Center,
+                                        horizontal: HorizontalAlign::Center,
+                                    },
+                                ),
+                                ..TextBundle::default()
+                            })
+                            .insert(ThemeColor::ButtonText);
+                    });
+            }
+        });
+}
+
+// Each button steps its setting on to the next value. A theme set by name in
+// the settings file is remembered, so it can be picked again.
+fn handle_settings_ui_buttons(
+    mut settings: ResMut<Settings>,
+    mut app_state: ResMut<State<AppState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    mut custom_theme: Local<Option<String>>,
+    query: Query<(&Interaction, &SettingsItem), Changed<Interaction>>,
+) {
+    for (interaction, item) in query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        match item {
+            SettingsItem::Back => app_state.set(AppState::MainMenu).unwrap(),
+            item => settings.cycle(*item, &mut custom_theme),
+        }
+        mouse_input.reset(MouseButton::Left);
+    }
+}
+
+fn handle_settings_back_key(
+    mut app_state: ResMut<State<AppState>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.set(AppState::MainMenu).unwrap();
+        keyboard_input.reset(KeyCode::Escape);
+    }
+}
+
+fn update_settings_labels(
+    settings: Res<Settings>,
+    buttons: Query<(&SettingsItem, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (item, children) in buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = settings.label(*item);
+            }
+        }
+    }
+}
+
+fn menu_scale(windows: &Windows) -> f32 {
+    windows
+        .get_primary()
+        .map_or(1., |win| (win.height() / MENU_HEIGHT).min(1.))
+}
+
+// Shrinks or grows the settings menu as the window is resized
+fn scale_settings_menu(
+    mut resized_events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut buttons: Query<(&mut Style, &Children), With<SettingsItem>>,
+    mut titles: Query<&mut Text, With<SettingsTitle>>,
+    mut texts: Query<&mut Text, Without<SettingsTitle>>,
+) {
+    let win = match windows.get_primary() {
+        Some(win) => win,
+        None => return,
+    };
+    let primary_resized = resized_events.iter().any(|event| event.id == win.id());
+    if !primary_resized {
+        return;
+    }
+
+    let scale = menu_scale(&windows);
+    for mut text in titles.iter_mut() {
+        text.sections[0].style.font_size = TITLE_FONT_SIZE * scale;
+    }
+    for (mut style, children) in buttons.iter_mut() {
+        style.size.height = Val::Px(BUTTON_HEIGHT * scale);
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].style.font_size = BUTTON_FONT_SIZE * scale;
+            }
+        }
+    }
+}
+
+fn close_settings_menu(mut commands: Commands, query: Query<Entity, With<SettingsMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
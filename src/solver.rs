@@ -0,0 +1,346 @@
+use std::collections::VecDeque;
+
+use crate::board::BitBoard;
+use crate::model::BoardLine;
+
+// Gives up on boards that need more guesses than this, since some random
+// boards take exponential time to prove unique
+const MAX_GUESSES: usize = 500;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Solution {
+    None,
+    Unique(BitBoard),
+    Multiple,
+    // The guess limit ran out before uniqueness could be decided
+    TooHard,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CellState {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+// What is known about each cell; a cell is never both filled and empty
+#[derive(Clone)]
+struct Knowledge {
+    filled: BitBoard,
+    empty: BitBoard,
+}
+
+impl Knowledge {
+    fn new(size: usize) -> Self {
+        Self {
+            filled: BitBoard::new(size),
+            empty: BitBoard::new(size),
+        }
+    }
+
+    fn state(&self, row: usize, col: usize) -> CellState {
+        if self.filled.get(row, col) {
+            CellState::Filled
+        } else if self.empty.get(row, col) {
+            CellState::Empty
+        } else {
+            CellState::Unknown
+        }
+    }
+
+    fn set(&mut self, row: usize, col: usize, state: CellState) {
+        match state {
+            CellState::Filled => self.filled.set(row, col, true),
+            CellState::Empty => self.empty.set(row, col, true),
+            CellState::Unknown => {}
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum LineRef {
+    Row(usize),
+    Col(usize),
+}
+
+impl LineRef {
+    // Every row and column, interleaved
+    fn all(size: usize) -> VecDeque<LineRef> {
+        (0..size)
+            .flat_map(|i| [LineRef::Row(i), LineRef::Col(i)])
+            .collect()
+    }
+}
+
+// Finds every cell of a line that is the same in all arrangements of its
+// clues consistent with what is already known. Scratch space is kept between
+// calls so solving a line does not allocate.
+#[derive(Default)]
+struct LineSolver {
+    // forward[i * (k + 1) + j]: the first i cells (plus a virtual empty cell
+    // at the end of the line) can hold the first j blocks, ending empty
+    forward: Vec<bool>,
+    // backward[i * (k + 1) + j]: cells i.. can hold blocks j..
+    backward: Vec<bool>,
+    // empties_before[i]: known-empty cells among the first i
+    empties_before: Vec<usize>,
+    fill_starts: Vec<i32>,
+    can_empty: Vec<bool>,
+}
+
+impl LineSolver {
+    // Returns false if no arrangement fits
+    fn solve(&mut self, cells: &mut [CellState], clues: &[usize]) -> bool {
+        let clues = match clues {
+            [0] => &[],
+            clues => clues,
+        };
+        let n = cells.len();
+        let m = n + 1;
+        let k = clues.len();
+        let width = k + 1;
+        let state = |i: usize| if i < n { cells[i] } else { CellState::Empty };
+
+        self.empties_before.clear();
+        self.empties_before.push(0);
+        for i in 0..m {
+            let empty = state(i) == CellState::Empty;
+            let before = self.empties_before[i];
+            self.empties_before.push(before + empty as usize);
+        }
+        let empties_before = &self.empties_before;
+        let can_fill_range = |from: usize, to: usize| empties_before[to] == empties_before[from];
+        let can_be_empty = |i: usize| state(i) != CellState::Filled;
+
+        self.forward.clear();
+        self.forward.resize((m + 1) * width, false);
+        self.forward[0] = true;
+        for i in 1..=m {
+            if !can_be_empty(i - 1) {
+                continue;
+            }
+            for j in 0..=k {
+                let mut ok = self.forward[(i - 1) * width + j];
+                // Or cell i - 1 is the gap after block j - 1
+                if !ok && j > 0 && i > clues[j - 1] {
+                    let start = i - 1 - clues[j - 1];
+                    ok = self.forward[start * width + j - 1] && can_fill_range(start, i - 1);
+                }
+                self.forward[i * width + j] = ok;
+            }
+        }
+        if !self.forward[m * width + k] {
+            return false;
+        }
+
+        self.backward.clear();
+        self.backward.resize((m + 1) * width, false);
+        self.backward[m * width + k] = true;
+        for i in (0..m).rev() {
+            for j in 0..=k {
+                let mut ok = can_be_empty(i) && self.backward[(i + 1) * width + j];
+                // Or block j starts here, followed by an empty cell
+                let end = clues.get(j).map(|len| i + len).filter(|&end| end < m);
+                if let Some(end) = end.filter(|_| !ok) {
+                    ok = can_fill_range(i, end)
+                        && can_be_empty(end)
+                        && self.backward[(end + 1) * width + j + 1];
+                }
+                self.backward[i * width + j] = ok;
+            }
+        }
+
+        // Mark every cell some valid block placement covers, using the
+        // difference of block starts and ends
+        self.fill_starts.clear();
+        self.fill_starts.resize(m + 1, 0);
+        self.can_empty.clear();
+        self.can_empty.resize(n, false);
+        for (j, &len) in clues.iter().enumerate() {
+            for start in 0..m.saturating_sub(len) {
+                let end = start + len;
+                if self.forward[start * width + j]
+                    && can_fill_range(start, end)
+                    && can_be_empty(end)
+                    && self.backward[(end + 1) * width + j + 1]
+                {
+                    self.fill_starts[start] += 1;
+                    self.fill_starts[end] -= 1;
+                }
+            }
+        }
+        for (c, can_empty) in self.can_empty.iter_mut().enumerate() {
+            *can_empty = can_be_empty(c)
+                && (0..=k).any(|j| {
+                    self.forward[(c + 1) * width + j] && self.backward[(c + 1) * width + j]
+                });
+        }
+
+        let mut covering = 0;
+        for (c, cell) in cells.iter_mut().enumerate() {
+            covering += self.fill_starts[c];
+            let can_fill = covering > 0;
+            match (can_fill, self.can_empty[c]) {
+                (true, false) => *cell = CellState::Filled,
+                (false, true) => *cell = CellState::Empty,
+                (false, false) => return false,
+                (true, true) => {}
+            }
+        }
+        true
+    }
+}
+
+pub struct Solver<'a> {
+    row_clues: &'a [Vec<usize>],
+    col_clues: &'a [Vec<usize>],
+    guesses: usize,
+    line_solver: LineSolver,
+    line: Vec<CellState>,
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(row_clues: &'a [Vec<usize>], col_clues: &'a [Vec<usize>]) -> Self {
+        Self {
+            row_clues,
+            col_clues,
+            guesses: 0,
+            line_solver: LineSolver::default(),
+            line: Vec::new(),
+        }
+    }
+
+    // Solves the clues, telling apart boards with one solution from those
+    // with none or several
+    pub fn solve(self) -> Solution {
+        self.solve_counting_guesses().0
+    }
+
+    // Also says how many cells had to be guessed, beyond what line by line
+    // logic could work out, which is a rough measure of how hard the puzzle is
+    pub fn solve_counting_guesses(mut self) -> (Solution, usize) {
+        let size = self.row_clues.len();
+        if self.col_clues.len() != size {
+            return (Solution::None, 0);
+        }
+        let mut found = None;
+        let solution = match self.search(Knowledge::new(size), LineRef::all(size), &mut found) {
+            Err(()) => Solution::TooHard,
+            Ok(0) => Solution::None,
+            Ok(1) => Solution::Unique(found.expect("a solution was counted")),
+            Ok(_) => Solution::Multiple,
+        };
+        (solution, self.guesses)
+    }
+
+    // Works out what line by line logic can without guessing, giving the
+    // line it found no way to fill if the clues contradict each other. Clues
+    // that don't make a square board have no one line to blame.
+    pub fn find_contradiction(mut self) -> Option<BoardLine> {
+        let size = self.row_clues.len();
+        if self.col_clues.len() != size {
+            return None;
+        }
+        let line = self
+            .propagate(&mut Knowledge::new(size), LineRef::all(size))
+            .err()?;
+        Some(match line {
+            LineRef::Row(row) => BoardLine::Row(row),
+            LineRef::Col(col) => BoardLine::Col(col),
+        })
+    }
+
+    // Counts solutions up to two, keeping the first one found
+    fn search(
+        &mut self,
+        mut knowledge: Knowledge,
+        dirty: VecDeque<LineRef>,
+        found: &mut Option<BitBoard>,
+    ) -> Result<usize, ()> {
+        if self.propagate(&mut knowledge, dirty).is_err() {
+            return Ok(0);
+        }
+
+        let size = self.row_clues.len();
+        let unknown = (0..size)
+            .flat_map(|row| (0..size).map(move |col| (row, col)))
+            .find(|&(row, col)| knowledge.state(row, col) == CellState::Unknown);
+        let Some((row, col)) = unknown else {
+            if found.is_none() {
+                *found = Some(knowledge.filled);
+            }
+            return Ok(1);
+        };
+
+        self.guesses += 1;
+        if self.guesses > MAX_GUESSES {
+            return Err(());
+        }
+        let mut count = 0;
+        for guess in [CellState::Filled, CellState::Empty] {
+            let mut branch = knowledge.clone();
+            branch.set(row, col, guess);
+            let dirty = VecDeque::from([LineRef::Row(row), LineRef::Col(col)]);
+            count += self.search(branch, dirty, found)?;
+            if count > 1 {
+                break;
+            }
+        }
+        Ok(count)
+    }
+
+    // Solves lines until nothing changes, failing with the line that can't
+    // be filled on a contradiction
+    fn propagate(
+        &mut self,
+        knowledge: &mut Knowledge,
+        mut dirty: VecDeque<LineRef>,
+    ) -> Result<(), LineRef> {
+        let size = self.row_clues.len();
+        let mut queued = vec![false; 2 * size];
+        for line in &dirty {
+            queued[Self::queue_index(*line, size)] = true;
+        }
+
+        while let Some(line) = dirty.pop_front() {
+            queued[Self::queue_index(line, size)] = false;
+            let (clues, cell_at): (_, &dyn Fn(usize) -> (usize, usize)) = match line {
+                LineRef::Row(row) => (&self.row_clues[row], &move |i| (row, i)),
+                LineRef::Col(col) => (&self.col_clues[col], &move |i| (i, col)),
+            };
+
+            self.line.clear();
+            self.line
+                .extend((0..size).map(cell_at).map(|(r, c)| knowledge.state(r, c)));
+            let before = self.line.clone();
+            if !self.line_solver.solve(&mut self.line, clues) {
+                return Err(line);
+            }
+
+            for (i, (&after, before)) in self.line.iter().zip(before).enumerate() {
+                if after == before {
+                    continue;
+                }
+                let (row, col) = cell_at(i);
+                knowledge.set(row, col, after);
+                let crossing = match line {
+                    LineRef::Row(_) => LineRef::Col(col),
+                    LineRef::Col(_) => LineRef::Row(row),
+                };
+                let index = Self::queue_index(crossing, size);
+                if !queued[index] {
+                    queued[index] = true;
+                    dirty.push_back(crossing);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn queue_index(line: LineRef, size: usize) -> usize {
+        match line {
+            LineRef::Row(row) => row,
+            LineRef::Col(col) => size + col,
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::audio::{play_queued_audio_system, Audio, AudioOutput, AudioSource, Decodable};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use rodio::{Decoder, Sample, Source};
+
+use crate::game::{CellChanged, CellType, LineCompleted, Mark, Puzzle, PuzzleSolved};
+use crate::settings::{MistakeMode, Settings};
+use crate::AppState;
+
+// Where sounds are looked for, relative to the assets folder. Each is named
+// after what it is played for (`fill.wav`, `music.ogg`, ...), and any that
+// are missing are simply not played.
+const SOUND_FOLDER: &str = "sounds";
+const MUSIC: &str = "music";
+
+// Sound effects and background music, played through a `Mixer`. Needs
+// Bevy's `AudioPlugin`, which is part of `DefaultPlugins`.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<AudioOutput<Playback>>()
+            .add_asset::<Playback>()
+            .init_resource::<Audio<Playback>>()
+            .init_resource::<Mixer>()
+            .init_resource::<ChannelGains>()
+            .add_event::<PlaySound>()
+            .add_startup_system(load_sounds)
+            .add_system(follow_sound_settings.before("mix"))
+            .add_system(update_gains.label("mix"))
+            .add_system(start_music.after("mix"))
+            .add_system(play_sounds.after("mix"))
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(cue_sounds.after("track_lines").before("mix"))
+                    .with_system(handle_mute_key),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                play_queued_audio_system::<Playback>.exclusive_system(),
+            );
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Sound {
+    Fill,
+    Cross,
+    Erase,
+    LineCompleted,
+    Mistake,
+    Win,
+}
+
+impl Sound {
+    // The sound's file name in the sound folder, without its extension
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sound::Fill => "fill",
+            Sound::Cross => "cross",
+            Sound::Erase => "erase",
+            Sound::LineCompleted => "line",
+            Sound::Mistake => "mistake",
+            Sound::Win => "win",
+        }
+    }
+}
+
+// Send to play a sound effect. Moves on the board send these on their own.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlaySound(pub Sound);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SoundCategory {
+    Effects,
+    Music,
+}
+
+// A volume, from 0 to 1, and whether it is muted
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self {
+            volume: 1.,
+            muted: false,
+        }
+    }
+}
+
+impl Channel {
+    fn gain(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.volume
+        }
+    }
+}
+
+// How loud each category of sound plays. `master` applies on top of the
+// others. Changes reach sounds that are already playing, including the
+// music. `SoundPlugin` keeps it in step with the settings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mixer {
+    pub master: Channel,
+    pub effects: Channel,
+    pub music: Channel,
+}
+
+impl Mixer {
+    pub fn channel(&self, category: SoundCategory) -> Channel {
+        match category {
+            SoundCategory::Effects => self.effects,
+            SoundCategory::Music => self.music,
+        }
+    }
+
+    // The factor a category's samples are scaled by
+    pub fn gain(&self, category: SoundCategory) -> f32 {
+        self.master.gain() * self.channel(category).gain()
+    }
+}
+
+impl From<&Settings> for Mixer {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            master: Channel {
+                volume: settings.volume,
+                muted: settings.muted,
+            },
+            effects: Channel {
+                volume: settings.effects_volume,
+                muted: false,
+            },
+            music: Channel {
+                volume: settings.music_volume,
+                muted: false,
+            },
+        }
+    }
+}
+
+// Each category's gain as f32 bits, shared with the audio thread so that
+// sounds follow the mixer while they play
+#[derive(Default)]
+struct ChannelGains {
+    effects: Arc<AtomicU32>,
+    music: Arc<AtomicU32>,
+}
+
+impl ChannelGains {
+    fn get(&self, category: SoundCategory) -> &Arc<AtomicU32> {
+        match category {
+            SoundCategory::Effects => &self.effects,
+            SoundCategory::Music => &self.music,
+        }
+    }
+}
+
+// A loaded sound being played in some category, and so at that category's
+// gain. Bevy's own `AudioSource` always plays at full volume.
+#[derive(TypeUuid)]
+#[uuid = "4f1a7c0e-9d62-4b8e-a1f3-2c5d8e6b7a90"]
+struct Playback {
+    source: AudioSource,
+    gain: Arc<AtomicU32>,
+    looping: bool,
+}
+
+impl Decodable for Playback {
+    type Decoder = MixedSource;
+    type DecoderItem = i16;
+
+    fn decoder(&self) -> MixedSource {
+        MixedSource {
+            decoder: decode(&self.source),
+            source: self.source.clone(),
+            gain: self.gain.clone(),
+            looping: self.looping,
+        }
+    }
+}
+
+// Files that can't be decoded play as silence rather than panicking
+fn decode(source: &AudioSource) -> Option<Decoder<Cursor<AudioSource>>> {
+    Decoder::new(Cursor::new(source.clone())).ok()
+}
+
+struct MixedSource {
+    decoder: Option<Decoder<Cursor<AudioSource>>>,
+    source: AudioSource,
+    gain: Arc<AtomicU32>,
+    // Starts again from the beginning at the end, forever
+    looping: bool,
+}
+
+impl Iterator for MixedSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        let sample = match self.decoder.as_mut()?.next() {
+            Some(sample) => sample,
+            None if self.looping => {
+                self.decoder = decode(&self.source);
+                self.decoder.as_mut()?.next()?
+            }
+            None => return None,
+        };
+        Some(sample.amplify(f32::from_bits(self.gain.load(Ordering::Relaxed))))
+    }
+}
+
+impl Source for MixedSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.decoder.as_ref()?.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.decoder.as_ref().map_or(1, Source::channels)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.decoder.as_ref().map_or(44100, Source::sample_rate)
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        if self.looping {
+            None
+        } else {
+            self.decoder.as_ref()?.total_duration()
+        }
+    }
+}
+
+// The sounds found in the sound folder, by name
+#[derive(Default)]
+struct SoundLibrary {
+    clips: HashMap<String, Handle<AudioSource>>,
+    music_started: bool,
+}
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Without a sound folder the game is silent
+    let handles = asset_server.load_folder(SOUND_FOLDER).unwrap_or_default();
+    let clips = handles
+        .into_iter()
+        .filter_map(|handle| {
+            let path = asset_server.get_handle_path(handle.id)?;
+            let name = path.path().file_stem()?.to_str()?.to_string();
+            Some((name, handle.typed()))
+        })
+        .collect();
+    commands.insert_resource(SoundLibrary {
+        clips,
+        music_started: false,
+    });
+}
+
+fn follow_sound_settings(settings: Res<Settings>, mut mixer: ResMut<Mixer>) {
+    if settings.is_changed() {
+        *mixer = Mixer::from(&*settings);
+    }
+}
+
+fn update_gains(mixer: Res<Mixer>, gains: Res<ChannelGains>) {
+    if !mixer.is_changed() {
+        return;
+    }
+    for category in [SoundCategory::Effects, SoundCategory::Music] {
+        let gain = mixer.gain(category);
+        gains.get(category).store(gain.to_bits(), Ordering::Relaxed);
+    }
+}
+
+// Plays the music on a loop once it has loaded, if there is any
+fn start_music(
+    mut library: ResMut<SoundLibrary>,
+    gains: Res<ChannelGains>,
+    sources: Res<Assets<AudioSource>>,
+    mut playbacks: ResMut<Assets<Playback>>,
+    audio: Res<Audio<Playback>>,
+) {
+    if library.music_started {
+        return;
+    }
+    if let Some(source) = library.clips.get(MUSIC).and_then(|clip| sources.get(clip)) {
+        audio.play(playbacks.add(Playback {
+            source: source.clone(),
+            gain: gains.music.clone(),
+            looping: true,
+        }));
+        library.music_started = true;
+    }
+}
+
+fn play_sounds(
+    mut play_sound: EventReader<PlaySound>,
+    library: Res<SoundLibrary>,
+    mixer: Res<Mixer>,
+    gains: Res<ChannelGains>,
+    sources: Res<Assets<AudioSource>>,
+    mut playbacks: ResMut<Assets<Playback>>,
+    audio: Res<Audio<Playback>>,
+) {
+    let silent = mixer.gain(SoundCategory::Effects) == 0.;
+    for PlaySound(sound) in play_sound.iter() {
+        if silent {
+            continue;
+        }
+        let clip = library.clips.get(sound.name());
+        if let Some(source) = clip.and_then(|clip| sources.get(clip)) {
+            audio.play(playbacks.add(Playback {
+                source: source.clone(),
+                gain: gains.effects.clone(),
+                looping: false,
+            }));
+        }
+    }
+}
+
+// Picks one sound for each frame's moves: winning, then a highlighted
+// mistake, then completing a line, then the mark itself. Crosses placed or
+// taken away by auto-cross make no sound of their own.
+fn cue_sounds(
+    settings: Res<Settings>,
+    puzzle: Res<Puzzle>,
+    mut cell_changed: EventReader<CellChanged>,
+    mut line_completed: EventReader<LineCompleted>,
+    mut puzzle_solved: EventReader<PuzzleSolved>,
+    mut play_sound: EventWriter<PlaySound>,
+) {
+    let highlight = settings.mistakes == MistakeMode::Highlight;
+    let mut mark_sound = None;
+    let mut mistake = false;
+    for change in cell_changed.iter().filter(|change| !change.is_automatic()) {
+        mistake |= highlight && puzzle.board.is_mistake(change.row, change.col, change.mark);
+        mark_sound.get_or_insert(match change.mark {
+            Some(Mark {
+                cell_type: CellType::Cross,
+                ..
+            }) => Sound::Cross,
+            Some(_) => Sound::Fill,
+            None => Sound::Erase,
+        });
+    }
+    let completed = line_completed.iter().count() > 0;
+    let solved = puzzle_solved.iter().count() > 0;
+
+    let sound = if solved {
+        Some(Sound::Win)
+    } else if mistake {
+        Some(Sound::Mistake)
+    } else if completed {
+        Some(Sound::LineCompleted)
+    } else {
+        mark_sound
+    };
+    if let Some(sound) = sound {
+        play_sound.send(PlaySound(sound));
+    }
+}
+
+fn handle_mute_key(mut keyboard_input: ResMut<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::M) {
+        settings.muted = !settings.muted;
+        keyboard_input.reset(KeyCode::M);
+    }
+}
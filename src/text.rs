@@ -0,0 +1,202 @@
+use crate::board::{clues, BitBoard};
+use crate::layout::ClueExtents;
+use crate::model::{Board, CellType};
+
+// Lines are drawn after every fifth cell, as on screen
+const BLOCK: usize = 5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Charset {
+    // Plain ASCII, for logs and terminals without Unicode
+    Ascii,
+    // Box drawing and block characters
+    Unicode,
+}
+
+struct Glyphs {
+    filled: char,
+    cross: char,
+    dot: char,
+    empty: char,
+    horizontal: char,
+    vertical: char,
+    // Corners and joins, left to right then top to bottom: the top edge, the
+    // lines between blocks and the bottom edge
+    top: [char; 3],
+    middle: [char; 3],
+    bottom: [char; 3],
+}
+
+impl Charset {
+    fn glyphs(&self) -> Glyphs {
+        match self {
+            Charset::Ascii => Glyphs {
+                filled: '#',
+                cross: 'x',
+                dot: 'o',
+                empty: '.',
+                horizontal: '-',
+                vertical: '|',
+                top: ['+', '+', '+'],
+                middle: ['+', '+', '+'],
+                bottom: ['+', '+', '+'],
+            },
+            Charset::Unicode => Glyphs {
+                filled: '█',
+                cross: '×',
+                dot: '•',
+                empty: '·',
+                horizontal: '─',
+                vertical: '│',
+                top: ['┌', '┬', '┐'],
+                middle: ['├', '┼', '┤'],
+                bottom: ['└', '┴', '┘'],
+            },
+        }
+    }
+}
+
+// A board's clues and grid laid out in characters, following the in-game
+// layout: row clues to the left of the grid, column clues stacked above it.
+// Each cell is as wide as the widest column clue number plus a space.
+pub struct TextBoard {
+    size: usize,
+    row_runs: Vec<Vec<usize>>,
+    col_runs: Vec<Vec<usize>>,
+    charset: Charset,
+    clues: ClueExtents,
+    cell_width: usize,
+}
+
+impl TextBoard {
+    pub fn new(row_runs: &[Vec<usize>], col_runs: &[Vec<usize>], charset: Charset) -> Self {
+        let clues = ClueExtents::measure(row_runs, col_runs);
+        Self {
+            size: row_runs.len(),
+            row_runs: row_runs.to_vec(),
+            col_runs: col_runs.to_vec(),
+            charset,
+            clues,
+            cell_width: clues.col_digits + 1,
+        }
+    }
+
+    pub fn for_board(board: &Board, charset: Charset) -> Self {
+        Self::new(board.row_runs(), board.col_runs(), charset)
+    }
+
+    // How many lines of text the board takes
+    pub fn height(&self) -> usize {
+        self.clues.col_lines + self.grid_lines() + 2
+    }
+
+    // Characters taken by the row clues, left of the grid
+    pub fn row_clue_width(&self) -> usize {
+        self.clues.row_chars
+    }
+
+    // Lines taken by the column clues, above the grid
+    pub fn col_clue_height(&self) -> usize {
+        self.clues.col_lines
+    }
+
+    fn grid_lines(&self) -> usize {
+        self.size + self.size.saturating_sub(1) / BLOCK
+    }
+
+    // The column and line where a cell's text starts
+    pub fn cell_position(&self, row: usize, col: usize) -> (usize, usize) {
+        let x = self.clues.row_chars + 2 + col * self.cell_width + col / BLOCK;
+        let y = self.clues.col_lines + 1 + row + row / BLOCK;
+        (x, y)
+    }
+
+    // The text of one cell, `cell_width` characters wide. Fills take the
+    // whole cell, so runs of them join up.
+    pub fn cell_text(&self, cell_type: Option<CellType>) -> String {
+        let glyphs = self.charset.glyphs();
+        let glyph = match cell_type {
+            Some(CellType::Filled) => {
+                return glyphs.filled.to_string().repeat(self.cell_width);
+            }
+            Some(CellType::Cross) => glyphs.cross,
+            Some(CellType::Dot) => glyphs.dot,
+            None => glyphs.empty,
+        };
+        format!("{:>1$}", glyph, self.cell_width)
+    }
+
+    // Every line of the board, with each cell drawn from `cell`
+    pub fn lines(&self, cell: impl Fn(usize, usize) -> Option<CellType>) -> Vec<String> {
+        let glyphs = self.charset.glyphs();
+        let indent = " ".repeat(self.clues.row_chars + 1);
+        let mut lines = Vec::with_capacity(self.height());
+
+        // Column clues are stacked to end just above the grid
+        for line in 0..self.clues.col_lines {
+            let mut text = format!("{} ", indent);
+            for (col, runs) in self.col_runs.iter().enumerate() {
+                if col > 0 && col % BLOCK == 0 {
+                    text.push(' ');
+                }
+                let first = self.clues.col_lines - runs.len();
+                match line.checked_sub(first) {
+                    Some(index) => text += &format!("{:>1$}", runs[index], self.cell_width),
+                    None => text += &" ".repeat(self.cell_width),
+                }
+            }
+            lines.push(text.trim_end().to_string());
+        }
+
+        let rule = |[left, join, right]: [char; 3]| {
+            let mut text = format!("{}{}", indent, left);
+            for col in 0..self.size {
+                if col > 0 && col % BLOCK == 0 {
+                    text.push(join);
+                }
+                text.extend(std::iter::repeat_n(glyphs.horizontal, self.cell_width));
+            }
+            text.push(right);
+            text
+        };
+
+        lines.push(rule(glyphs.top));
+        for (row, runs) in self.row_runs.iter().enumerate() {
+            if row > 0 && row % BLOCK == 0 {
+                lines.push(rule(glyphs.middle));
+            }
+            let clue: Vec<String> = runs.iter().map(usize::to_string).collect();
+            let mut text = format!(
+                "{:>2$} {}",
+                clue.join(" "),
+                glyphs.vertical,
+                self.clues.row_chars
+            );
+            for col in 0..self.size {
+                if col > 0 && col % BLOCK == 0 {
+                    text.push(glyphs.vertical);
+                }
+                text += &self.cell_text(cell(row, col));
+            }
+            text.push(glyphs.vertical);
+            lines.push(text);
+        }
+        lines.push(rule(glyphs.bottom));
+        lines
+    }
+}
+
+// A board as the player has marked it, for logs and tests
+pub fn render(board: &Board, charset: Charset) -> String {
+    let text = TextBoard::for_board(board, charset);
+    let lines = text.lines(|row, col| board.mark_at(row, col).map(|mark| mark.cell_type));
+    lines.join("\n") + "\n"
+}
+
+// A solution, with its clues, and every other cell left empty
+pub fn render_solution(solution: &BitBoard, charset: Charset) -> String {
+    let (row_runs, col_runs) = clues(solution);
+    let text = TextBoard::new(&row_runs, &col_runs, charset);
+    let lines = text.lines(|row, col| solution.get(row, col).then_some(CellType::Filled));
+    lines.join("\n") + "\n"
+}
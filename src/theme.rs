@@ -0,0 +1,417 @@
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Layer, Mark};
+use crate::settings::Settings;
+
+// The colors everything is drawn with, read from a `.theme.toml` file of
+// hex colors like `"#141a52"`. Every color must be given.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, TypeUuid)]
+#[uuid = "c8c0483d-3d0f-4649-aa3b-3eff9bd6012d"]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    #[serde(with = "hex_color")]
+    pub grid_background: Color,
+    #[serde(with = "hex_color")]
+    pub gridline: Color,
+    #[serde(with = "hex_color")]
+    pub mark: Color,
+    #[serde(with = "hex_color")]
+    pub tentative: Color,
+    #[serde(with = "hex_color")]
+    pub auto_cross: Color,
+    // Marks that contradict the solution, when mistakes are highlighted
+    #[serde(with = "hex_color")]
+    pub mistake: Color,
+    #[serde(with = "hex_color")]
+    pub clue: Color,
+    // Menu titles and other text drawn over the background
+    #[serde(with = "hex_color")]
+    pub text: Color,
+    // The puzzle code and timer shown while playing, and hints in menus
+    #[serde(with = "hex_color")]
+    pub hud: Color,
+    #[serde(with = "hex_color")]
+    pub button: Color,
+    #[serde(with = "hex_color")]
+    pub button_text: Color,
+    #[serde(with = "hex_color")]
+    pub error: Color,
+}
+
+// The built-in themes, which are also in the assets folder so that editing
+// them there takes effect while the game runs
+const BUILT_IN: [(&str, &str); 5] = [
+    ("dark", include_str!("../assets/themes/dark.theme.toml")),
+    ("light", include_str!("../assets/themes/light.theme.toml")),
+    (
+        "high-contrast",
+        include_str!("../assets/themes/high-contrast.theme.toml"),
+    ),
+    ("paper", include_str!("../assets/themes/paper.theme.toml")),
+    (
+        "colorblind",
+        include_str!("../assets/themes/colorblind.theme.toml"),
+    ),
+];
+
+// WCAG's minimum contrast ratios for text, and for shapes that must be told
+// apart from what is around them
+const TEXT_CONTRAST: f32 = 4.5;
+const GRAPHICS_CONTRAST: f32 = 3.;
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::named("dark").unwrap()
+    }
+}
+
+impl Theme {
+    // Names of the built-in themes, in the order the settings screen cycles
+    // through them
+    pub const NAMES: [&'static str; 5] = ["dark", "light", "high-contrast", "paper", "colorblind"];
+
+    // Built-in themes for low vision and color blindness, which pass every
+    // check in `contrast_problems`
+    pub const ACCESSIBLE: [&'static str; 2] = ["high-contrast", "colorblind"];
+
+    // A built-in theme, which is available before any assets load
+    pub fn named(name: &str) -> Option<Self> {
+        BUILT_IN
+            .iter()
+            .find(|(built_in, _)| *built_in == name)
+            .map(|(_, text)| Self::from_toml(text).expect("built-in themes are valid"))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    // Where a theme's file lives, relative to the assets folder
+    pub fn asset_path(name: &str) -> String {
+        format!("themes/{}.theme.toml", name)
+    }
+
+    // Pairs of colors that fall short of WCAG contrast: marks, crosses and
+    // mistakes against empty cells and against each other, and text against
+    // what it is drawn on. Mistakes also differ in shape when highlighted, so
+    // they need not contrast with tentative marks.
+    pub fn contrast_problems(&self) -> Vec<String> {
+        let pairs = [
+            (
+                "mark",
+                self.mark,
+                "grid_background",
+                self.grid_background,
+                TEXT_CONTRAST,
+            ),
+            (
+                "auto_cross",
+                self.auto_cross,
+                "grid_background",
+                self.grid_background,
+                GRAPHICS_CONTRAST,
+            ),
+            (
+                "tentative",
+                self.tentative,
+                "grid_background",
+                self.grid_background,
+                GRAPHICS_CONTRAST,
+            ),
+            (
+                "mistake",
+                self.mistake,
+                "grid_background",
+                self.grid_background,
+                GRAPHICS_CONTRAST,
+            ),
+            (
+                "mistake",
+                self.mistake,
+                "mark",
+                self.mark,
+                GRAPHICS_CONTRAST,
+            ),
+            (
+                "gridline",
+                self.gridline,
+                "grid_background",
+                self.grid_background,
+                GRAPHICS_CONTRAST,
+            ),
+            (
+                "clue",
+                self.clue,
+                "background",
+                self.background,
+                TEXT_CONTRAST,
+            ),
+            (
+                "text",
+                self.text,
+                "background",
+                self.background,
+                TEXT_CONTRAST,
+            ),
+            (
+                "hud",
+                self.hud,
+                "background",
+                self.background,
+                TEXT_CONTRAST,
+            ),
+            (
+                "error",
+                self.error,
+                "background",
+                self.background,
+                TEXT_CONTRAST,
+            ),
+            (
+                "button_text",
+                self.button_text,
+                "button",
+                self.button,
+                TEXT_CONTRAST,
+            ),
+        ];
+        pairs
+            .iter()
+            .filter_map(|&(name, color, against_name, against, minimum)| {
+                let ratio = contrast_ratio(color, against);
+                (ratio < minimum).then(|| {
+                    format!(
+                        "{} on {} has contrast {:.1}:1, below {}:1",
+                        name, against_name, ratio, minimum
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn mark_color(&self, layer: Layer) -> Color {
+        match layer {
+            Layer::Main => self.mark,
+            Layer::Tentative => self.tentative,
+            Layer::Auto => self.auto_cross,
+        }
+    }
+
+    pub fn cell_color(&self, mark: Mark, mistake: bool) -> Color {
+        if mistake {
+            self.mistake
+        } else {
+            self.mark_color(mark.layer)
+        }
+    }
+
+    pub fn color(&self, which: ThemeColor) -> Color {
+        match which {
+            ThemeColor::GridBackground => self.grid_background,
+            ThemeColor::Gridline => self.gridline,
+            ThemeColor::Tentative => self.tentative,
+            ThemeColor::Clue => self.clue,
+            ThemeColor::Text => self.text,
+            ThemeColor::Hud => self.hud,
+            ThemeColor::Button => self.button,
+            ThemeColor::ButtonText => self.button_text,
+            ThemeColor::Error => self.error,
+        }
+    }
+}
+
+// WCAG relative luminance of an sRGB color
+fn relative_luminance(color: Color) -> f32 {
+    let [r, g, b, _] = color.as_rgba_f32().map(|c| {
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+// From 1:1 for identical colors up to 21:1 for black on white
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+// Colors as `#rrggbb`, or `#rrggbbaa` when not opaque
+pub(crate) mod hex_color {
+    use bevy::prelude::Color;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.as_rgba_f32().map(|c| (c * 255.).round() as u8);
+        let hex = if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let hex = text.strip_prefix('#').unwrap_or(&text);
+        if hex.len() != 6 && hex.len() != 8 {
+            return Err(D::Error::custom(format!(
+                "`{}` is not a color like \"#1a2b3c\"",
+                text
+            )));
+        }
+        Color::hex(hex).map_err(|_| D::Error::custom(format!("`{}` is not a hex color", text)))
+    }
+}
+
+// Which of the theme's colors an entity is drawn in, so that it can be
+// recolored when the theme changes. Sets the color of its text, sprite or
+// UI node, whichever it has. Marks aren't tagged, since their color also
+// depends on their layer and on mistakes; `recolor_board` redraws them.
+#[derive(Component, Copy, Clone, Debug, PartialEq)]
+pub enum ThemeColor {
+    GridBackground,
+    Gridline,
+    Tentative,
+    Clue,
+    Text,
+    Hud,
+    Button,
+    ButtonText,
+    Error,
+}
+
+#[derive(Default)]
+struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let theme = Theme::from_toml(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(theme));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.toml"]
+    }
+}
+
+// The theme file named in the settings, which replaces the `Theme`
+// resource whenever it loads or changes
+struct ActiveTheme {
+    name: String,
+    handle: Handle<Theme>,
+}
+
+impl ActiveTheme {
+    fn load(name: &str, asset_server: &AssetServer) -> Self {
+        Self {
+            name: name.to_string(),
+            handle: asset_server.load(Theme::asset_path(name).as_str()),
+        }
+    }
+}
+
+// Loads the theme chosen in the settings from the assets folder, reloading
+// it when the file changes if the asset server is watching for changes, and
+// recolors everything tagged with a `ThemeColor` when the theme changes
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .add_startup_system(load_theme)
+            .add_system(follow_theme_setting.before("apply_theme"))
+            .add_system(apply_theme_assets.label("apply_theme"))
+            .add_system(recolor_themed.after("apply_theme"));
+    }
+}
+
+fn load_theme(mut commands: Commands, settings: Res<Settings>, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ActiveTheme::load(&settings.theme, &asset_server));
+}
+
+// Switches theme when another is picked in the settings. Built-in themes
+// apply at once, while others wait for their file to load.
+fn follow_theme_setting(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    mut active: ResMut<ActiveTheme>,
+    mut theme: ResMut<Theme>,
+) {
+    if !settings.is_changed() || settings.theme == active.name {
+        return;
+    }
+    *active = ActiveTheme::load(&settings.theme, &asset_server);
+    if let Some(built_in) = Theme::named(&settings.theme) {
+        *theme = built_in;
+    }
+}
+
+fn apply_theme_assets(
+    mut events: EventReader<AssetEvent<Theme>>,
+    themes: Res<Assets<Theme>>,
+    active: Res<ActiveTheme>,
+    mut theme: ResMut<Theme>,
+) {
+    for event in events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        if *handle != active.handle {
+            continue;
+        }
+        if let Some(loaded) = themes.get(handle) {
+            if *loaded != *theme {
+                *theme = loaded.clone();
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn recolor_themed(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    mut query: Query<(
+        &ThemeColor,
+        Option<&mut Text>,
+        Option<&mut Sprite>,
+        Option<&mut UiColor>,
+    )>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    commands.insert_resource(ClearColor(theme.background));
+    for (which, text, sprite, ui_color) in query.iter_mut() {
+        let color = theme.color(*which);
+        if let Some(mut text) = text {
+            for section in &mut text.sections {
+                section.style.color = color;
+            }
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+        }
+        if let Some(mut ui_color) = ui_color {
+            ui_color.0 = color;
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::Color;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{
+    self, Attribute, Print, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+
+use crate::code::PuzzleCode;
+use crate::game::format_time;
+use crate::model::{Board, CellType, Layer};
+use crate::replay::{self, Replay};
+use crate::settings::{MistakeMode, Settings};
+use crate::text::{Charset, TextBoard};
+use crate::theme::Theme;
+
+// How often the clock is checked for a new second while waiting for keys
+const TICK: Duration = Duration::from_millis(250);
+
+const HELP: &str = "ARROWS/HJKL MOVE  SPACE FILL  X CROSS  . MAYBE  U/R UNDO/REDO  \
+                    T TENTATIVE  A AUTO-CROSS  W SAVE REPLAY  Q QUIT";
+
+pub struct TuiArgs {
+    pub code: PuzzleCode,
+    pub charset: Charset,
+}
+
+// Reads `--tui [code] [--ascii]` from the command line. Without a code, a
+// random puzzle of the size in the settings is played.
+pub fn options_from_args() -> Option<TuiArgs> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|arg| arg == "--tui")?;
+    let code = args
+        .get(flag + 1)
+        .filter(|arg| !arg.starts_with("--"))
+        .and_then(|code| match code.parse() {
+            Ok(code) => Some(code),
+            Err(err) => {
+                eprintln!("Playing a random puzzle instead of {}: {}", code, err);
+                None
+            }
+        });
+    let board_size = Settings::default_path()
+        .map(|path| Settings::load_or_default(&path))
+        .unwrap_or_default()
+        .board_size;
+    Some(TuiArgs {
+        code: code.unwrap_or_else(|| PuzzleCode::random(board_size)),
+        charset: if args.iter().any(|arg| arg == "--ascii") {
+            Charset::Ascii
+        } else {
+            Charset::Unicode
+        },
+    })
+}
+
+// Puts the terminal back as it was, however the game ends
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            style::ResetColor,
+            cursor::Show,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// A game in the terminal, played on the same `Board` as the windowed game
+struct Session {
+    code: PuzzleCode,
+    board: Board,
+    text: TextBoard,
+    charset: Charset,
+    cursor: (usize, usize),
+    settings: Settings,
+    settings_path: Option<PathBuf>,
+    theme: Theme,
+    started: Instant,
+    // How long the puzzle took, once it is solved
+    solved_in: Option<Duration>,
+    replay: Replay,
+    // Where the replay was saved, or why it wasn't
+    notice: Option<String>,
+}
+
+impl Session {
+    fn new(code: PuzzleCode, charset: Charset) -> Self {
+        let settings_path = Settings::default_path();
+        let settings = settings_path
+            .as_deref()
+            .map(Settings::load_or_default)
+            .unwrap_or_default();
+        let mut board = Board::new(code.solution());
+        if settings.auto_cross {
+            board.set_auto_cross(true);
+            board.settle(true);
+        }
+        Self {
+            text: TextBoard::for_board(&board, charset),
+            board,
+            replay: Replay::new(code.clone()),
+            code,
+            charset,
+            cursor: (0, 0),
+            theme: settings.theme(),
+            settings,
+            settings_path,
+            started: Instant::now(),
+            solved_in: None,
+            notice: None,
+        }
+    }
+
+    // Handles a key, giving false once the player quits
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            || (ctrl && key.code == KeyCode::Char('c'))
+        {
+            return false;
+        }
+        if key.code == KeyCode::Char('w') {
+            self.notice = Some(match replay::save(&self.replay) {
+                Ok(path) => format!("SAVED THE REPLAY TO {}", path.display()),
+                Err(err) => format!("COULDN'T SAVE THE REPLAY: {}", err),
+            });
+            return true;
+        }
+        if self.solved_in.is_some() {
+            if key.code == KeyCode::Char('n') {
+                *self = Self::new(PuzzleCode::random(self.board.size()), self.charset);
+            }
+            return true;
+        }
+
+        let (row, col) = self.cursor;
+        let last = self.board.size() - 1;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.cursor.0 = row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor.0 = (row + 1).min(last),
+            KeyCode::Left | KeyCode::Char('h') => self.cursor.1 = col.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.cursor.1 = (col + 1).min(last),
+            KeyCode::Char(' ') | KeyCode::Char('f') => {
+                self.board.toggle_mark(row, col, CellType::Filled)
+            }
+            KeyCode::Char('x') => self.board.toggle_mark(row, col, CellType::Cross),
+            KeyCode::Char('.') => self.board.toggle_mark(row, col, CellType::Dot),
+            KeyCode::Char('z') if ctrl && key.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.board.redo()
+            }
+            KeyCode::Char('z') if ctrl => self.board.undo(),
+            KeyCode::Char('y') if ctrl => self.board.redo(),
+            KeyCode::Char('u') => self.board.undo(),
+            KeyCode::Char('r') => self.board.redo(),
+            KeyCode::Char('t') => self.board.tentative = !self.board.tentative,
+            KeyCode::Enter => self.board.commit_tentative(),
+            KeyCode::Backspace | KeyCode::Delete => self.board.discard_tentative(),
+            KeyCode::Char('a') => {
+                self.settings.auto_cross = !self.settings.auto_cross;
+                self.board.set_auto_cross(self.settings.auto_cross);
+                if let Some(path) = &self.settings_path {
+                    let _ = self.settings.save(path);
+                }
+            }
+            _ => {}
+        }
+
+        self.board.settle(self.settings.auto_cross);
+        let elapsed = self.started.elapsed();
+        self.replay.record(elapsed, self.board.take_actions());
+        if self.board.is_solved() {
+            self.solved_in = Some(elapsed);
+        }
+        true
+    }
+
+    // The seconds shown on the clock, which stop once the puzzle is solved
+    fn clock_secs(&self) -> u64 {
+        let elapsed = self.solved_in.unwrap_or_else(|| self.started.elapsed());
+        elapsed.as_secs()
+    }
+
+    // Repaints the whole game. Clearing the screen first is only needed when
+    // the layout may have changed, as every line is otherwise drawn over.
+    fn draw(&self, out: &mut impl Write, clear: bool) -> io::Result<()> {
+        let theme = &self.theme;
+        queue!(out, SetBackgroundColor(to_terminal(theme.background)))?;
+        if clear {
+            queue!(out, terminal::Clear(ClearType::All))?;
+        }
+
+        let lines = self
+            .text
+            .lines(|row, col| self.board.mark_at(row, col).map(|mark| mark.cell_type));
+        let clue_lines = self.text.col_clue_height();
+        for (y, line) in lines.iter().enumerate() {
+            let color = if y < clue_lines {
+                theme.clue
+            } else {
+                theme.gridline
+            };
+            queue!(
+                out,
+                cursor::MoveTo(0, y as u16),
+                SetForegroundColor(to_terminal(color)),
+                Print(line)
+            )?;
+        }
+
+        // Row clues, then the marks, over the gridlines
+        let size = self.board.size();
+        let clue_width = self.text.row_clue_width();
+        for row in 0..size {
+            let y = self.text.cell_position(row, 0).1;
+            queue!(
+                out,
+                cursor::MoveTo(0, y as u16),
+                SetForegroundColor(to_terminal(theme.clue)),
+                Print(&lines[y][..clue_width])
+            )?;
+        }
+        let highlight = self.settings.mistakes == MistakeMode::Highlight;
+        for row in 0..size {
+            for col in 0..size {
+                let mark = self.board.mark_at(row, col);
+                let selected = self.cursor == (row, col) && self.solved_in.is_none();
+                if mark.is_none() && !selected {
+                    continue;
+                }
+                let color = match mark {
+                    Some(mark) => {
+                        let mistake = highlight && self.board.is_mistake(row, col, Some(mark));
+                        theme.cell_color(mark, mistake)
+                    }
+                    None => theme.gridline,
+                };
+                let (x, y) = self.text.cell_position(row, col);
+                queue!(
+                    out,
+                    cursor::MoveTo(x as u16, y as u16),
+                    SetForegroundColor(to_terminal(color))
+                )?;
+                if selected {
+                    queue!(out, SetAttribute(Attribute::Reverse))?;
+                }
+                queue!(
+                    out,
+                    Print(self.text.cell_text(mark.map(|mark| mark.cell_type))),
+                    SetAttribute(Attribute::Reset),
+                    SetBackgroundColor(to_terminal(theme.background))
+                )?;
+            }
+        }
+
+        self.queue_clock(out)?;
+        let y = self.text.height() as u16 + 1;
+        queue!(
+            out,
+            SetForegroundColor(to_terminal(theme.hud)),
+            cursor::MoveTo(0, y + 2),
+            Print(HELP),
+            cursor::MoveTo(0, y + 3),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        if let Some(notice) = &self.notice {
+            queue!(out, Print(notice))?;
+        }
+        queue!(
+            out,
+            cursor::MoveTo(0, y + 1),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        if self.solved_in.is_some() {
+            queue!(
+                out,
+                cursor::MoveTo(0, y + 1),
+                SetForegroundColor(to_terminal(theme.text)),
+                Print("SOLVED - N FOR A NEW PUZZLE, Q TO QUIT")
+            )?;
+        } else if self.board.tentative {
+            queue!(
+                out,
+                cursor::MoveTo(0, y + 1),
+                SetForegroundColor(to_terminal(theme.mark_color(Layer::Tentative))),
+                Print("TENTATIVE - ENTER TO COMMIT, BACKSPACE TO DISCARD")
+            )?;
+        }
+        out.flush()
+    }
+
+    // Repaints only the line with the clock, as it ticks
+    fn draw_clock(&self, out: &mut impl Write) -> io::Result<()> {
+        self.queue_clock(out)?;
+        out.flush()
+    }
+
+    fn queue_clock(&self, out: &mut impl Write) -> io::Result<()> {
+        let y = self.text.height() as u16 + 1;
+        queue!(
+            out,
+            cursor::MoveTo(0, y),
+            SetForegroundColor(to_terminal(self.theme.hud)),
+            Print(format!(
+                "{}  {}  AUTO-CROSS {}",
+                self.code,
+                format_time(self.clock_secs()),
+                if self.settings.auto_cross {
+                    "ON"
+                } else {
+                    "OFF"
+                }
+            )),
+            terminal::Clear(ClearType::UntilNewLine)
+        )
+    }
+}
+
+fn to_terminal(color: Color) -> style::Color {
+    let [r, g, b, _] = color.as_rgba_f32().map(|c| (c * 255.).round() as u8);
+    style::Color::Rgb { r, g, b }
+}
+
+// Plays a puzzle in the terminal until the player quits
+pub fn run(args: &TuiArgs) -> io::Result<()> {
+    let mut session = Session::new(args.code.clone(), args.charset);
+    let _terminal = RawTerminal::enter()?;
+    let mut out = io::stdout();
+    session.draw(&mut out, true)?;
+    let mut shown_secs = session.clock_secs();
+    loop {
+        if !event::poll(TICK)? {
+            if session.clock_secs() != shown_secs {
+                session.draw_clock(&mut out)?;
+                shown_secs = session.clock_secs();
+            }
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                let code = session.code.clone();
+                if !session.handle_key(key) {
+                    return Ok(());
+                }
+                // A new puzzle's clues may take a different amount of room
+                session.draw(&mut out, session.code != code)?;
+            }
+            Event::Resize(..) => session.draw(&mut out, true)?,
+            _ => continue,
+        }
+        shown_secs = session.clock_secs();
+    }
+}
@@ -0,0 +1,359 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+
+use crate::game::{CellState, CellType, Game, GridPos, Layer, Mark, Puzzle};
+use crate::theme::{Theme, ThemeColor};
+use crate::AppState;
+
+// Inserted when a game ends, for the win screen. `revealed` is set when the
+// player gave up rather than solved it.
+pub struct Solution {
+    pub revealed: bool,
+}
+
+// How the finished board is animated: marks that aren't part of the picture
+// fade out while the solution's cells grow in, rippling out from the middle,
+// then the board holds still for a moment before the win menu appears
+const FADE_SECONDS: f32 = 0.4;
+const RIPPLE_START_SECONDS: f32 = 0.2;
+const RIPPLE_SECONDS: f32 = 1.;
+const GROW_SECONDS: f32 = 0.35;
+const HOLD_SECONDS: f32 = 0.6;
+const REVEAL_SECONDS: f32 = RIPPLE_START_SECONDS + RIPPLE_SECONDS + GROW_SECONDS + HOLD_SECONDS;
+
+// How much of the board shows through the win menu
+const WIN_MENU_VEIL_ALPHA: f32 = 0.55;
+
+// A full-color picture of the solution, which its cells turn to as the
+// board is revealed. Insert it with the puzzle it belongs to; it is removed
+// when the game closes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevealPicture {
+    size: usize,
+    // Row by row
+    colors: Vec<Color>,
+}
+
+impl RevealPicture {
+    pub fn new(size: usize, colors: Vec<Color>) -> Self {
+        assert_eq!(colors.len(), size * size, "one color for every cell");
+        Self { size, colors }
+    }
+
+    // Scales an RGBA image to the board, taking each cell's color from the
+    // pixel at its centre. Images in other formats, or whose data doesn't
+    // fill them, give `None`.
+    pub fn from_image(image: &Image, size: usize) -> Option<Self> {
+        let linear = match image.texture_descriptor.format {
+            TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Rgba8Unorm => true,
+            _ => return None,
+        };
+        let width = image.texture_descriptor.size.width as usize;
+        let height = image.texture_descriptor.size.height as usize;
+        if width == 0 || height == 0 || image.data.len() != width * height * 4 {
+            return None;
+        }
+        let mut colors = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let x = (col * 2 + 1) * width / (size * 2);
+                let y = (row * 2 + 1) * height / (size * 2);
+                let i = (y * width + x) * 4;
+                let [r, g, b, a] = [0, 1, 2, 3].map(|channel| image.data[i + channel]);
+                colors.push(if linear {
+                    let [r, g, b, a] = [r, g, b, a].map(|c| c as f32 / 255.);
+                    Color::rgba_linear(r, g, b, a)
+                } else {
+                    Color::rgba_u8(r, g, b, a)
+                });
+            }
+        }
+        Some(Self { size, colors })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn color(&self, row: usize, col: usize) -> Color {
+        self.colors[row * self.size + col]
+    }
+}
+
+// The animation of the finished board, and the screen shown after it
+pub struct WinScreenPlugin;
+
+impl Plugin for WinScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Solved).with_system(start_reveal))
+            .add_system_set(
+                SystemSet::on_update(AppState::Solved)
+                    .with_system(skip_reveal.before("animate_reveal"))
+                    .with_system(animate_reveal.label("animate_reveal")),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Solved).with_system(end_reveal))
+            .add_system_set(SystemSet::on_enter(AppState::WinMenu).with_system(setup_win_menu))
+            .add_system_set(
+                SystemSet::on_update(AppState::WinMenu).with_system(handle_win_ui_buttons),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::WinMenu).with_system(close_win_menu));
+    }
+}
+
+// Time since the board was solved
+struct RevealTimer(f32);
+
+// A mark that isn't part of the picture, fading out from its own color
+#[derive(Component)]
+struct FadingMark(Color);
+
+// One of the solution's cells, growing in once its turn in the ripple comes
+// and turning from the mark color to the picture's
+#[derive(Component)]
+struct RevealCell {
+    delay: f32,
+    from: Color,
+    to: Color,
+}
+
+fn start_reveal(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    theme: Res<Theme>,
+    puzzle: Res<Puzzle>,
+    picture: Option<Res<RevealPicture>>,
+    cells: Query<(Entity, &GridPos, &CellState, &Sprite, &Visibility)>,
+) {
+    let solution = puzzle.board.get_solution();
+    let size = solution.size();
+    let picture = picture.filter(|picture| picture.size() == size);
+
+    // Only fills on the main layer stay, where the solution has them, to be
+    // grown over
+    let filled = Some(Mark {
+        cell_type: CellType::Filled,
+        layer: Layer::Main,
+    });
+    for (entity, pos, state, sprite, visibility) in cells.iter() {
+        let stays = state.0 == filled && solution.get(pos.row, pos.col);
+        if visibility.is_visible && !stays {
+            commands.entity(entity).insert(FadingMark(sprite.color));
+        }
+    }
+    // Large boards have no cell sprites to fade, so their marks go at once
+    puzzle.grid.erase_texture(&mut images, &theme);
+
+    let middle = (size as f32 - 1.) / 2.;
+    let farthest = Vec2::splat(middle).length().max(1.);
+    for row in 0..size {
+        for col in 0..size {
+            if !solution.get(row, col) {
+                continue;
+            }
+            let (center, side) = puzzle.grid.cell_square(row, col);
+            let distance = Vec2::new(row as f32 - middle, col as f32 - middle).length();
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.mark,
+                        custom_size: Some(Vec2::splat(side)),
+                        ..Default::default()
+                    },
+                    transform: Transform {
+                        translation: center.extend(2.),
+                        scale: Vec3::ZERO,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(RevealCell {
+                    delay: RIPPLE_START_SECONDS + RIPPLE_SECONDS * distance / farthest,
+                    from: theme.mark,
+                    to: picture
+                        .as_ref()
+                        .map_or(theme.mark, |picture| picture.color(row, col)),
+                })
+                .insert(Game);
+        }
+    }
+    commands.insert_resource(RevealTimer(0.));
+}
+
+// Any key or click jumps to the end
+fn skip_reveal(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut timer: ResMut<RevealTimer>,
+) {
+    if keyboard_input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some()
+    {
+        timer.0 = REVEAL_SECONDS;
+    }
+}
+
+// Steps the animation on, and shows the win menu when it ends
+fn animate_reveal(
+    time: Res<Time>,
+    mut timer: ResMut<RevealTimer>,
+    mut app_state: ResMut<State<AppState>>,
+    mut fading: Query<(&FadingMark, &mut Sprite), Without<RevealCell>>,
+    mut cells: Query<(&RevealCell, &mut Sprite, &mut Transform)>,
+) {
+    timer.0 += time.delta_seconds();
+    let elapsed = timer.0;
+
+    let fade = (1. - elapsed / FADE_SECONDS).max(0.);
+    for (FadingMark(color), mut sprite) in fading.iter_mut() {
+        sprite.color = *color;
+        sprite.color.set_a(color.a() * fade);
+    }
+
+    for (cell, mut sprite, mut transform) in cells.iter_mut() {
+        let progress = ((elapsed - cell.delay) / GROW_SECONDS).clamp(0., 1.);
+        transform.scale = Vec3::splat(ease_out_back(progress));
+        sprite.color = mix(cell.from, cell.to, progress);
+    }
+
+    if elapsed >= REVEAL_SECONDS {
+        app_state.set(AppState::WinMenu).unwrap();
+    }
+}
+
+// Overshoots a little before settling at 1
+fn ease_out_back(t: f32) -> f32 {
+    const OVERSHOOT: f32 = 1.7;
+    let t = t - 1.;
+    1. + t * t * ((OVERSHOOT + 1.) * t + OVERSHOOT)
+}
+
+fn mix(from: Color, to: Color, t: f32) -> Color {
+    let [from, to] = [from, to].map(|color| Vec4::from(color.as_rgba_f32()));
+    let mixed = from.lerp(to, t);
+    Color::rgba(mixed.x, mixed.y, mixed.z, mixed.w)
+}
+
+fn end_reveal(mut commands: Commands) {
+    commands.remove_resource::<RevealTimer>();
+}
+
+#[derive(Component)]
+struct WinMenu;
+
+#[derive(Component)]
+enum WinMenuItem {
+    MainMenu,
+}
+
+fn setup_win_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    solution: Res<Solution>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 40.0,
+        color: theme.text,
+    };
+    let text_alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+    let mut veil = theme.background;
+    veil.set_a(WIN_MENU_VEIL_ALPHA);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::SpaceEvenly,
+                ..Default::default()
+            },
+            color: veil.into(),
+            ..Default::default()
+        })
+        .insert(WinMenu)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        if solution.revealed {
+                            "SOLUTION"
+                        } else {
+                            "CORRECT"
+                        },
+                        text_style.clone(),
+                        text_alignment,
+                    ),
+                    ..Default::default()
+                })
+                .insert(ThemeColor::Text);
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Percent(17.0),
+                            height: Val::Px(30.0),
+                        },
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceEvenly,
+                        ..Style::default()
+                    },
+                    color: theme.button.into(),
+                    ..ButtonBundle::default()
+                })
+                .insert(ThemeColor::Button)
+                .insert(WinMenuItem::MainMenu)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style::default(),
+                            text: Text::with_section(
+                                "MAIN MENU",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 20.0,
+                                    color: theme.button_text,
+                                },
+                                TextAlignment {
+                                    vertical: VerticalAlign::Center,
+                                    horizontal: HorizontalAlign::Center,
+                                },
+                            ),
+                            ..TextBundle::default()
+                        })
+                        .insert(ThemeColor::ButtonText);
+                });
+        });
+}
+
+fn close_win_menu(mut commands: Commands, query: Query<Entity, With<WinMenu>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Solution>();
+}
+
+fn handle_win_ui_buttons(
+    mut app_state: ResMut<State<AppState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    query: Query<(&Interaction, &WinMenuItem)>,
+) {
+    query.for_each(|(interaction, item)| match interaction {
+        Interaction::Clicked => match item {
+            WinMenuItem::MainMenu => {
+                app_state.replace(AppState::MainMenu).unwrap();
+                mouse_input.reset(MouseButton::Left);
+            }
+        },
+        Interaction::Hovered => {}
+        _ => {}
+    });
+}
@@ -0,0 +1,32 @@
+// Examples of puzzle codes
+
+use rust_nonogram::PuzzleCode;
+
+// Seeded codes must build the same board in every version of the game and
+// its dependencies, or shared codes, packs and replays change puzzle
+#[test]
+fn seeded_codes_keep_their_solution() {
+    let code: PuzzleCode = "10-5eed".parse().unwrap();
+    let solution = code.solution();
+    let rows: Vec<String> = (0..solution.size())
+        .map(|row| {
+            let cells = solution.row(row).iter();
+            cells.map(|filled| if filled { '#' } else { '.' }).collect()
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            "..###..#..",
+            "#..#......",
+            "######.###",
+            ".###.#..#.",
+            "#.#....#..",
+            "#.##....#.",
+            "#..#.#.#.#",
+            "#..#....#.",
+            "....#....#",
+            "...#.#....",
+        ]
+    );
+}
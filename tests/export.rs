@@ -0,0 +1,39 @@
+// Examples of drawing boards to files
+
+use rust_nonogram::export::{self, ExportBoard, ExportError, ExportOptions, MAX_PNG_PIXELS};
+use rust_nonogram::PuzzleCode;
+
+fn options(cell_size: u32) -> ExportOptions {
+    ExportOptions {
+        cell_size,
+        ..Default::default()
+    }
+}
+
+fn pixels(board: &ExportBoard, options: &ExportOptions) -> usize {
+    let (width, height) = export::image_size(board, options);
+    width as usize * height as usize
+}
+
+// PNGs one cell size past the pixel limit are refused rather than drawn,
+// however far past it they are
+#[test]
+fn pngs_past_the_size_limit_are_refused() {
+    let largest: PuzzleCode = "255-5eed".parse().unwrap();
+    let board = ExportBoard::blank(&largest.solution());
+    let fits = (1..)
+        .take_while(|&cell_size| pixels(&board, &options(cell_size)) <= MAX_PNG_PIXELS)
+        .last()
+        .unwrap();
+
+    let small = ExportBoard::blank(&"10-5eed".parse::<PuzzleCode>().unwrap().solution());
+    for (board, cell_size) in [(&board, fits + 1), (&board, 260), (&small, 100_000)] {
+        let options = options(cell_size);
+        match export::to_png(board, &options) {
+            Err(ExportError::TooLarge { width, height }) => {
+                assert_eq!((width, height), export::image_size(board, &options));
+            }
+            other => panic!("cell size {} gave {:?}", cell_size, other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,621 @@
+// Drives the game's states without a window or GPU, using Bevy's minimal
+// plugins plus a stand-in primary window
+
+use std::time::Duration;
+
+use bevy::app::Events;
+use bevy::asset::AssetPlugin;
+use bevy::audio::AudioPlugin;
+use bevy::ecs::event::ManualEventReader;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::{ElementState, InputPlugin};
+use bevy::prelude::*;
+use bevy::window::{WindowId, WindowPlugin};
+use raw_window_handle::{RawWindowHandle, WebHandle};
+
+use rust_nonogram::model::Action;
+use rust_nonogram::replay::Step;
+use rust_nonogram::{
+    AppState, CellType, Game, MainCamera, MenuItem, MistakeMode, Mixer, NonogramConfig,
+    NonogramPlugins, PauseItem, PlaySound, Puzzle, PuzzleCode, PuzzleFile, PuzzleProblems,
+    PuzzleSource, Replay, ReplayPlayback, RevealPicture, Settings, SettingsFile, SettingsItem,
+    Sound, SoundCategory, Theme, ThemeColor,
+};
+
+fn headless_app(config: NonogramConfig) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(AudioPlugin)
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            exit_on_close: false,
+        })
+        .add_asset::<Image>()
+        .insert_resource(config)
+        .add_plugins(NonogramPlugins);
+
+    let window = Window::new(
+        WindowId::primary(),
+        &WindowDescriptor::default(),
+        600,
+        600,
+        1.,
+        None,
+        RawWindowHandle::Web(WebHandle::empty()),
+    );
+    app.world.get_resource_mut::<Windows>().unwrap().add(window);
+    app.world
+        .spawn()
+        .insert_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+    app
+}
+
+fn state(app: &App) -> AppState {
+    app.world
+        .get_resource::<State<AppState>>()
+        .unwrap()
+        .current()
+        .clone()
+}
+
+// Runs frames until the state settles, since transitions take effect on the
+// frame after they are requested
+fn settle(app: &mut App) {
+    for _ in 0..3 {
+        app.update();
+    }
+}
+
+fn game_entities(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<Entity, With<Game>>()
+        .iter(&app.world)
+        .count()
+}
+
+fn click<T: Component + PartialEq + std::fmt::Debug>(app: &mut App, clicked: T) {
+    let mut buttons = app.world.query::<(&mut Interaction, &T)>();
+    let mut found = false;
+    for (mut interaction, item) in buttons.iter_mut(&mut app.world) {
+        if *item == clicked {
+            *interaction = Interaction::Clicked;
+            found = true;
+        }
+    }
+    assert!(found, "no {:?} button", clicked);
+}
+
+// Fills every cell of the solution on the main layer
+fn solve(app: &mut App) {
+    let mut puzzle = app.world.get_resource_mut::<Puzzle>().unwrap();
+    let solution = puzzle.board.get_solution().clone();
+    for row in 0..solution.size() {
+        for col in 0..solution.size() {
+            if solution.get(row, col) {
+                puzzle.board.toggle_mark(row, col, CellType::Filled);
+            }
+        }
+    }
+}
+
+#[test]
+fn play_from_menu_to_win() {
+    let mut app = headless_app(NonogramConfig::default());
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::MainMenu);
+
+    click(&mut app, MenuItem::Play);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+    assert!(app.world.get_resource::<Puzzle>().is_some());
+    assert!(game_entities(&mut app) > 0);
+
+    solve(&mut app);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::Solved);
+
+    // The win menu is shown over the finished board
+    skip_reveal(&mut app);
+    assert_eq!(state(&app), AppState::WinMenu);
+    assert!(game_entities(&mut app) > 0);
+
+    click_all(&mut app);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::MainMenu);
+    assert_eq!(game_entities(&mut app), 0);
+    assert!(app.world.get_resource::<Puzzle>().is_none());
+    assert!(app.world.get_resource::<PuzzleCode>().is_none());
+}
+
+#[test]
+fn wrong_marks_do_not_win() {
+    let code: PuzzleCode = "5-5eed".parse().unwrap();
+    let mut app = headless_app(NonogramConfig {
+        initial_state: AppState::InGame,
+        puzzle: PuzzleSource::Code(code.clone()),
+        ..Default::default()
+    });
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+    assert_eq!(app.world.get_resource::<PuzzleCode>(), Some(&code));
+
+    solve(&mut app);
+    let solution = code.solution();
+    let empty = (0..5)
+        .flat_map(|row| (0..5).map(move |col| (row, col)))
+        .find(|&(row, col)| !solution.get(row, col))
+        .expect("the puzzle has an empty cell");
+    app.world
+        .get_resource_mut::<Puzzle>()
+        .unwrap()
+        .board
+        .toggle_mark(empty.0, empty.1, CellType::Filled);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+
+    // Crossing it instead still solves the puzzle
+    app.world
+        .get_resource_mut::<Puzzle>()
+        .unwrap()
+        .board
+        .toggle_mark(empty.0, empty.1, CellType::Cross);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::Solved);
+}
+
+#[test]
+fn win_menu_returns_to_main_menu() {
+    let mut app = headless_app(NonogramConfig {
+        initial_state: AppState::InGame,
+        ..Default::default()
+    });
+    settle(&mut app);
+    solve(&mut app);
+    settle(&mut app);
+    skip_reveal(&mut app);
+    assert_eq!(state(&app), AppState::WinMenu);
+
+    click_all(&mut app);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::MainMenu);
+}
+
+// Presses the only button on screen
+fn click_all(app: &mut App) {
+    let mut buttons = app.world.query::<&mut Interaction>();
+    for mut interaction in buttons.iter_mut(&mut app.world) {
+        *interaction = Interaction::Clicked;
+    }
+}
+
+// Any key skips the animation of the finished board
+fn skip_reveal(app: &mut App) {
+    press_key(app, KeyCode::Space);
+}
+
+// Sends a press and release through the input events, since the input
+// system clears any state set on `Input` directly
+fn press_key(app: &mut App, key: KeyCode) {
+    for state in [ElementState::Pressed, ElementState::Released] {
+        app.world
+            .get_resource_mut::<Events<KeyboardInput>>()
+            .unwrap()
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key),
+                state,
+            });
+        app.update();
+    }
+    settle(app);
+}
+
+fn press_escape(app: &mut App) {
+    press_key(app, KeyCode::Escape);
+}
+
+fn visible_game_entities(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<&Visibility, With<Game>>()
+        .iter(&app.world)
+        .filter(|visibility| visibility.is_visible)
+        .count()
+}
+
+fn start_game(code: &str) -> App {
+    let mut app = headless_app(NonogramConfig {
+        initial_state: AppState::InGame,
+        puzzle: PuzzleSource::Code(code.parse().unwrap()),
+        ..Default::default()
+    });
+    settle(&mut app);
+    app
+}
+
+#[test]
+fn pausing_hides_the_board_until_resumed() {
+    let mut app = start_game("5-5eed");
+    app.world
+        .get_resource_mut::<Puzzle>()
+        .unwrap()
+        .board
+        .toggle_mark(0, 0, CellType::Cross);
+    settle(&mut app);
+    let visible = visible_game_entities(&mut app);
+
+    press_escape(&mut app);
+    assert_eq!(state(&app), AppState::Paused);
+    assert_eq!(visible_game_entities(&mut app), 0);
+    assert!(app.world.get_resource::<Puzzle>().is_some());
+
+    click(&mut app, PauseItem::Resume);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+    assert_eq!(visible_game_entities(&mut app), visible);
+}
+
+#[test]
+fn restart_clears_the_board() {
+    let mut app = start_game("5-5eed");
+    let before = game_entities(&mut app);
+    app.world
+        .get_resource_mut::<Puzzle>()
+        .unwrap()
+        .board
+        .toggle_mark(0, 0, CellType::Cross);
+    settle(&mut app);
+
+    press_escape(&mut app);
+    click(&mut app, PauseItem::Restart);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+    assert_eq!(game_entities(&mut app), before);
+    assert_eq!(
+        app.world.get_resource::<PuzzleCode>(),
+        Some(&"5-5eed".parse().unwrap())
+    );
+    let puzzle = app.world.get_resource::<Puzzle>().unwrap();
+    assert!(puzzle.board.mark_at(0, 0).is_none());
+}
+
+#[test]
+fn leaving_from_pause_closes_the_game() {
+    let mut app = start_game("5-5eed");
+    press_escape(&mut app);
+    click(&mut app, PauseItem::MainMenu);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::MainMenu);
+    assert_eq!(game_entities(&mut app), 0);
+    assert!(app.world.get_resource::<Puzzle>().is_none());
+}
+
+#[test]
+fn revealing_paints_the_picture_over_the_board() {
+    let mut app = start_game("5-5eed");
+    let picture = RevealPicture::new(5, vec![Color::ORANGE; 25]);
+    app.world.insert_resource(picture);
+    app.world
+        .get_resource_mut::<Puzzle>()
+        .unwrap()
+        .board
+        .toggle_mark(0, 0, CellType::Cross);
+    settle(&mut app);
+    let visible = visible_game_entities(&mut app);
+
+    // The board comes back from behind the pause menu for the animation
+    press_escape(&mut app);
+    click(&mut app, PauseItem::Reveal);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::Solved);
+    assert!(visible_game_entities(&mut app) > visible);
+
+    skip_reveal(&mut app);
+    assert_eq!(state(&app), AppState::WinMenu);
+    let mut sprites = app.world.query_filtered::<&Sprite, With<Game>>();
+    let colors: Vec<Color> = sprites
+        .iter(&app.world)
+        .map(|sprite| sprite.color)
+        .collect();
+    let puzzle = app.world.get_resource::<Puzzle>().unwrap();
+    let solution = puzzle.board.get_solution();
+    let filled = (0..5)
+        .flat_map(|row| (0..5).map(move |col| (row, col)))
+        .filter(|&(row, col)| solution.get(row, col))
+        .count();
+    let orange = colors.iter().filter(|&&color| color == Color::ORANGE);
+    assert_eq!(orange.count(), filled);
+    // The cross has faded away
+    assert!(colors
+        .iter()
+        .all(|color| color.a() == 0. || color.a() == 1.));
+    assert!(colors.iter().any(|color| color.a() == 0.));
+}
+
+#[test]
+fn puzzle_files_are_played_with_their_palette() {
+    let path = std::path::Path::new("assets/puzzles/heart.json");
+    let puzzle = PuzzleFile::load(path).unwrap();
+    let mut app = headless_app(NonogramConfig {
+        initial_state: AppState::InGame,
+        puzzle: PuzzleSource::File(Box::new(puzzle.clone())),
+        ..Default::default()
+    });
+    settle(&mut app);
+    assert_eq!(app.world.get_resource::<PuzzleCode>(), Some(&puzzle.code()));
+    let board = &app.world.get_resource::<Puzzle>().unwrap().board;
+    assert_eq!(board.get_solution(), &puzzle.solution);
+    assert_eq!(
+        app.world.get_resource::<RevealPicture>(),
+        puzzle.picture.as_ref()
+    );
+
+    press_escape(&mut app);
+    click(&mut app, PauseItem::Restart);
+    settle(&mut app);
+    assert_eq!(
+        app.world.get_resource::<RevealPicture>(),
+        puzzle.picture.as_ref()
+    );
+}
+
+#[test]
+fn unplayable_puzzles_show_an_error_screen() {
+    let mut app = headless_app(NonogramConfig {
+        initial_state: AppState::InGame,
+        puzzle: PuzzleSource::Code(PuzzleCode::Seeded { size: 0, seed: 1 }),
+        ..Default::default()
+    });
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::PuzzleError);
+    assert!(app.world.get_resource::<Puzzle>().is_none());
+    let problems = app.world.get_resource::<PuzzleProblems>().unwrap();
+    assert_eq!(problems.problems, ["size 0 is not between 1 and 255"]);
+
+    // The problems follow the theme, like the rest of the screen
+    let light = Theme::named("light").unwrap();
+    app.insert_resource(light.clone());
+    settle(&mut app);
+    let mut texts = app.world.query::<(&Text, &ThemeColor)>();
+    let errors: Vec<Color> = texts
+        .iter(&app.world)
+        .filter(|(_, which)| **which == ThemeColor::Error)
+        .map(|(text, _)| text.sections[0].style.color)
+        .collect();
+    assert_eq!(errors, [light.error]);
+
+    press_escape(&mut app);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::MainMenu);
+    assert!(app.world.get_resource::<PuzzleProblems>().is_none());
+}
+
+#[test]
+fn replays_are_played_back_at_the_chosen_speed() {
+    // Every filled cell but the last is filled straight away, and the last
+    // one a second later
+    let code: PuzzleCode = "5-5eed".parse().unwrap();
+    let solution = code.solution();
+    let mut filled = Vec::new();
+    for row in 0..5 {
+        for col in 0..5 {
+            if solution.get(row, col) {
+                filled.push((row, col));
+            }
+        }
+    }
+    let mut replay = Replay::new(code.clone());
+    for (index, &(row, col)) in filled.iter().enumerate() {
+        let time = if index + 1 == filled.len() {
+            Duration::from_secs(1)
+        } else {
+            Duration::ZERO
+        };
+        let action = Action::Toggle {
+            row,
+            col,
+            cell_type: CellType::Filled,
+            tentative: false,
+        };
+        replay.steps.push(Step { time, action });
+    }
+
+    let mut app = headless_app(NonogramConfig {
+        initial_state: AppState::InGame,
+        puzzle: PuzzleSource::Code(code),
+        ..Default::default()
+    });
+    app.insert_resource(ReplayPlayback::new(replay.clone()));
+    settle(&mut app);
+    let marked = |app: &App| {
+        let board = &app.world.get_resource::<Puzzle>().unwrap().board;
+        filled
+            .iter()
+            .filter(|&&(row, col)| board.mark_at(row, col).is_some())
+            .count()
+    };
+    assert_eq!(marked(&app), filled.len() - 1);
+
+    // Paused, the last step doesn't come however fast playback is
+    press_key(&mut app, KeyCode::Space);
+    for _ in 0..6 {
+        press_key(&mut app, KeyCode::RBracket);
+    }
+    let speed = app.world.get_resource::<ReplayPlayback>().unwrap().speed();
+    assert_eq!(speed, 16.);
+    std::thread::sleep(Duration::from_millis(100));
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+    assert_eq!(marked(&app), filled.len() - 1);
+
+    press_key(&mut app, KeyCode::Space);
+    std::thread::sleep(Duration::from_millis(100));
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::Solved);
+    assert!(app.world.get_resource::<ReplayPlayback>().is_none());
+    assert_eq!(app.world.get_resource::<Replay>(), Some(&replay));
+}
+
+#[test]
+fn new_puzzle_keeps_the_size() {
+    let mut app = start_game("7-5eed");
+    press_escape(&mut app);
+    click(&mut app, PauseItem::NewPuzzle);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::InGame);
+    let puzzle = app.world.get_resource::<Puzzle>().unwrap();
+    assert_eq!(puzzle.board.get_solution().size(), 7);
+    assert_ne!(
+        app.world.get_resource::<PuzzleCode>(),
+        Some(&"7-5eed".parse().unwrap())
+    );
+}
+
+#[test]
+fn settings_are_changed_from_the_menu_and_saved() {
+    let dir = std::env::temp_dir().join(format!("rust-nonogram-test-{}", std::process::id()));
+    let path = dir.join("settings.toml");
+    let mut app = headless_app(NonogramConfig::default());
+    app.insert_resource(SettingsFile(path.clone()));
+    settle(&mut app);
+
+    click(&mut app, MenuItem::Settings);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::Settings);
+    click(&mut app, SettingsItem::AutoCross);
+    click(&mut app, SettingsItem::BoardSize);
+    settle(&mut app);
+
+    let settings = app.world.get_resource::<Settings>().unwrap().clone();
+    assert!(settings.auto_cross);
+    assert_eq!(settings.board_size, 15);
+    assert_eq!(Settings::load(&path).unwrap(), settings);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // New games use the chosen size
+    click(&mut app, SettingsItem::Back);
+    settle(&mut app);
+    assert_eq!(state(&app), AppState::MainMenu);
+    click(&mut app, MenuItem::Play);
+    settle(&mut app);
+    let puzzle = app.world.get_resource::<Puzzle>().unwrap();
+    assert_eq!(puzzle.board.get_solution().size(), 15);
+}
+
+#[test]
+fn custom_themes_stay_in_the_theme_cycle() {
+    let mut app = headless_app(NonogramConfig::default());
+    app.insert_resource(Settings {
+        theme: "mine".to_string(),
+        ..Default::default()
+    });
+    settle(&mut app);
+    click(&mut app, MenuItem::Settings);
+    settle(&mut app);
+
+    let mut picked = Vec::new();
+    for _ in 0..=Theme::NAMES.len() {
+        click(&mut app, SettingsItem::Theme);
+        settle(&mut app);
+        picked.push(app.world.get_resource::<Settings>().unwrap().theme.clone());
+    }
+    let mut expected: Vec<&str> = Theme::NAMES.to_vec();
+    expected.push("mine");
+    assert_eq!(picked, expected);
+}
+
+#[test]
+fn picking_a_theme_recolors_the_screen() {
+    let mut app = headless_app(NonogramConfig::default());
+    settle(&mut app);
+    click(&mut app, MenuItem::Settings);
+    settle(&mut app);
+    click(&mut app, SettingsItem::Theme);
+    settle(&mut app);
+
+    let light = Theme::named("light").unwrap();
+    assert_eq!(app.world.get_resource::<Theme>(), Some(&light));
+    assert_eq!(
+        app.world.get_resource::<ClearColor>().unwrap().0,
+        light.background
+    );
+    let mut texts = app.world.query::<(&Text, &ThemeColor)>();
+    for (text, which) in texts.iter(&app.world) {
+        assert_eq!(text.sections[0].style.color, light.color(*which));
+    }
+}
+
+// Runs a frame and gives the sounds it played
+fn frame_sounds(app: &mut App, reader: &mut ManualEventReader<PlaySound>) -> Vec<Sound> {
+    app.update();
+    let events = app.world.get_resource::<Events<PlaySound>>().unwrap();
+    reader.iter(events).map(|event| event.0).collect()
+}
+
+#[test]
+fn moves_play_sounds() {
+    let mut app = start_game("5-5eed");
+    app.world.get_resource_mut::<Settings>().unwrap().mistakes = MistakeMode::Highlight;
+    let mut reader = ManualEventReader::default();
+    frame_sounds(&mut app, &mut reader);
+
+    let solution = app
+        .world
+        .get_resource::<Puzzle>()
+        .unwrap()
+        .board
+        .get_solution()
+        .clone();
+    let (empty_row, empty_col) = (0..5)
+        .flat_map(|row| (0..5).map(move |col| (row, col)))
+        .find(|&(row, col)| !solution.get(row, col))
+        .unwrap();
+
+    let mut toggle = |app: &mut App, row, col, cell_type| {
+        let mut puzzle = app.world.get_resource_mut::<Puzzle>().unwrap();
+        puzzle.board.toggle_mark(row, col, cell_type);
+        frame_sounds(app, &mut reader)
+    };
+    assert_eq!(
+        toggle(&mut app, empty_row, empty_col, CellType::Dot),
+        [Sound::Fill]
+    );
+    assert_eq!(
+        toggle(&mut app, empty_row, empty_col, CellType::Cross),
+        [Sound::Cross]
+    );
+    assert_eq!(
+        toggle(&mut app, empty_row, empty_col, CellType::Cross),
+        [Sound::Erase]
+    );
+    assert_eq!(
+        toggle(&mut app, empty_row, empty_col, CellType::Filled),
+        [Sound::Mistake]
+    );
+    assert_eq!(
+        toggle(&mut app, empty_row, empty_col, CellType::Filled),
+        [Sound::Erase]
+    );
+
+    solve(&mut app);
+    assert_eq!(frame_sounds(&mut app, &mut reader), [Sound::Win]);
+}
+
+#[test]
+fn mute_key_silences_the_mixer() {
+    let mut app = start_game("5-5eed");
+    let gain = |app: &App| {
+        let mixer = app.world.get_resource::<Mixer>().unwrap();
+        mixer.gain(SoundCategory::Effects)
+    };
+    assert!(gain(&app) > 0.);
+
+    press_key(&mut app, KeyCode::M);
+    assert!(app.world.get_resource::<Settings>().unwrap().muted);
+    assert_eq!(gain(&app), 0.);
+
+    press_key(&mut app, KeyCode::M);
+    assert!(gain(&app) > 0.);
+}
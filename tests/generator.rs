@@ -0,0 +1,28 @@
+// Examples of generating puzzle packs
+
+use rust_nonogram::generator::{generate_pack, PackOptions};
+
+fn options(threads: usize) -> PackOptions {
+    PackOptions {
+        count: 8,
+        size: 10,
+        seed: 0x5eed,
+        threads: Some(threads),
+    }
+}
+
+// Each puzzle's seeds come from its index alone, so spreading the work over
+// more threads mustn't change the pack or what was rejected on the way
+#[test]
+fn packs_are_the_same_on_any_number_of_threads() {
+    let (single_codes, single) = generate_pack(&options(1));
+    let (many_codes, many) = generate_pack(&options(4));
+
+    assert_eq!(single_codes.len(), single.generated);
+    assert_eq!(single_codes, many_codes);
+    assert_eq!(single.generated, many.generated);
+    assert_eq!(single.failed, many.failed);
+    assert_eq!(single.boards_tried, many.boards_tried);
+    assert_eq!(single.rejected_multiple, many.rejected_multiple);
+    assert_eq!(single.rejected_too_hard, many.rejected_too_hard);
+}
@@ -0,0 +1,282 @@
+// Randomized checks of the clue model, the solver and the puzzle code format
+
+use std::time::Duration;
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use rust_nonogram::board::{clues, count_runs, line_matches, BitBoard};
+use rust_nonogram::book::{self, BookOptions, Paper};
+use rust_nonogram::export::{self, ExportBoard, ExportOptions};
+use rust_nonogram::model::{Board, CellType, Layer};
+use rust_nonogram::replay::Replay;
+use rust_nonogram::solver::{Solution, Solver};
+use rust_nonogram::{PuzzleCode, PuzzleFile};
+
+// Square boards up to `max_size`, at densities from empty to full so that
+// empty and full lines come up often
+fn board(max_size: usize) -> impl Strategy<Value = BitBoard> {
+    (1..=max_size, 0.0..=1.0).prop_flat_map(|(size, density)| {
+        vec(prop::bool::weighted(density), size * size)
+            .prop_map(move |cells| BitBoard::from_fn(size, |row, col| cells[row * size + col]))
+    })
+}
+
+type Clues = (Vec<Vec<usize>>, Vec<Vec<usize>>);
+
+// Counts the boards matching some clues, up to two, by trying every
+// arrangement of each row and checking the columns at the end
+fn brute_force_count((row_clues, col_clues): &Clues) -> usize {
+    let size = row_clues.len();
+    let row_options: Vec<Vec<u32>> = row_clues
+        .iter()
+        .map(|clues| {
+            (0..1u32 << size)
+                .filter(|&bits| {
+                    let row = BitBoard::from_fn(size, |_, col| bits & (1 << col) != 0);
+                    line_matches(row.row(0), clues)
+                })
+                .collect()
+        })
+        .collect();
+
+    fn count(row_options: &[Vec<u32>], col_clues: &[Vec<usize>], chosen: &mut Vec<u32>) -> usize {
+        let size = col_clues.len();
+        if chosen.len() == size {
+            let board = BitBoard::from_fn(size, |row, col| chosen[row] & (1 << col) != 0);
+            let columns_match = (0..size).all(|col| line_matches(board.col(col), &col_clues[col]));
+            return columns_match as usize;
+        }
+        let mut found = 0;
+        for &bits in &row_options[chosen.len()] {
+            chosen.push(bits);
+            found += count(row_options, col_clues, chosen);
+            chosen.pop();
+            if found > 1 {
+                break;
+            }
+        }
+        found
+    }
+    count(&row_options, col_clues, &mut Vec::new())
+}
+
+proptest! {
+    // Every clue number is written once, and PNGs come out the size of the
+    // board's layout
+    #[test]
+    fn exports_draw_every_clue(board in board(12), cell_size in 4u32..32, margin in 0u32..20) {
+        let options = ExportOptions { cell_size, margin, crosses: true };
+        let export = ExportBoard::solved(&board);
+        let (row_clues, col_clues) = clues(&board);
+        let numbers = row_clues.iter().chain(&col_clues).map(Vec::len).sum::<usize>();
+        prop_assert_eq!(export::to_svg(&export, &options).matches("<text").count(), numbers);
+
+        let png = export::to_png(&export, &options).unwrap();
+        let info = png::Decoder::new(png.as_slice()).read_info().unwrap().info().clone();
+        prop_assert_eq!((info.width, info.height), export::image_size(&export, &options));
+    }
+
+    // Every puzzle is titled once among the puzzles and once among the
+    // solutions, and the cross-reference table points at each object
+    #[test]
+    fn books_hold_every_puzzle(
+        boards in vec(board(12), 0..8),
+        letter in any::<bool>(),
+        solutions in any::<bool>(),
+    ) {
+        let paper = if letter { Paper::Letter } else { Paper::A4 };
+        let pdf = book::to_pdf(&boards, &BookOptions { paper, solutions });
+        let pdf = String::from_utf8(pdf).unwrap();
+        for number in 1..=boards.len() {
+            let titles = pdf.matches(&format!("({}. ", number)).count();
+            prop_assert_eq!(titles, if solutions { 2 } else { 1 });
+        }
+
+        let pages = pdf.matches("/Type /Page ").count();
+        let count = format!("/Count {} ", pages);
+        prop_assert!(pages >= 1 && pdf.contains(&count));
+        let start = pdf.rsplit("startxref\n").next().unwrap();
+        let xref: usize = start.lines().next().unwrap().parse().unwrap();
+        let entries: Vec<&str> = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .collect();
+        // The catalog, page tree and fonts, then each page and its contents
+        prop_assert_eq!(entries.len(), 4 + 2 * pages);
+        for (index, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            let object = format!("{} 0 obj", index + 1);
+            prop_assert!(pdf[offset..].starts_with(&object));
+        }
+    }
+
+    // Filling in the solution solves the board, and auto-cross crosses out
+    // every other cell
+    #[test]
+    fn filling_the_solution_solves_the_board(board in board(15)) {
+        let mut model = Board::new(board.clone());
+        model.set_auto_cross(true);
+        let size = board.size();
+        for (row, col) in (0..size * size).map(|i| (i / size, i % size)) {
+            if board.get(row, col) {
+                prop_assert!(!model.is_solved());
+                model.toggle_mark(row, col, CellType::Filled);
+                model.settle(true);
+            }
+        }
+        prop_assert!(model.is_solved());
+        for row in 0..size {
+            for col in 0..size {
+                let mark = model.mark_at(row, col).unwrap();
+                let layer = if board.get(row, col) { Layer::Main } else { Layer::Auto };
+                prop_assert_eq!(mark.layer, layer);
+            }
+        }
+    }
+
+    // Files written for a board load back as that board, in either format
+    #[test]
+    fn puzzle_files_round_trip(board in board(20)) {
+        let file = PuzzleFile::from_solution("Board", &board);
+        let json = PuzzleFile::from_json(&file.to_json()).unwrap();
+        let ron = PuzzleFile::from_ron(&file.to_ron()).unwrap();
+        prop_assert_eq!(&json, &file);
+        prop_assert_eq!(&ron, &file);
+        prop_assert_eq!(file.validate().unwrap().solution, board);
+    }
+
+    #[test]
+    fn clues_are_satisfied_by_their_board(board in board(40)) {
+        let (row_clues, col_clues) = clues(&board);
+        for i in 0..board.size() {
+            prop_assert!(line_matches(board.row(i), &row_clues[i]));
+            prop_assert!(line_matches(board.col(i), &col_clues[i]));
+        }
+    }
+
+    // An empty line's clue is `[0]`, and 0 never appears otherwise
+    #[test]
+    fn empty_lines_have_the_clue_zero(board in board(40)) {
+        for i in 0..board.size() {
+            let runs = count_runs(board.row(i));
+            let empty = board.row(i).iter().all(|filled| !filled);
+            prop_assert_eq!(empty, runs == [0]);
+            prop_assert!(!runs.is_empty());
+            prop_assert!(runs == [0] || !runs.contains(&0));
+        }
+    }
+
+    #[test]
+    fn runs_add_up_to_the_filled_cells(board in board(70)) {
+        for i in 0..board.size() {
+            let filled = board.row(i).iter().filter(|&filled| filled).count();
+            prop_assert_eq!(count_runs(board.row(i)).iter().sum::<usize>(), filled);
+        }
+    }
+
+    #[test]
+    fn solutions_satisfy_the_clues(board in board(15)) {
+        let clues = clues(&board);
+        match Solver::new(&clues.0, &clues.1).solve() {
+            Solution::Unique(solution) => {
+                prop_assert_eq!(rust_nonogram::board::clues(&solution), clues);
+                prop_assert_eq!(solution, board);
+            }
+            Solution::Multiple | Solution::TooHard => {}
+            Solution::None => prop_assert!(false, "clues taken from a board have a solution"),
+        }
+    }
+
+    #[test]
+    fn uniqueness_agrees_with_brute_force(board in board(5)) {
+        let clues = clues(&board);
+        let expected = brute_force_count(&clues);
+        match Solver::new(&clues.0, &clues.1).solve() {
+            Solution::Unique(_) => prop_assert_eq!(expected, 1),
+            Solution::Multiple => prop_assert!(expected > 1),
+            other => prop_assert!(false, "unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn impossible_clues_have_no_solution(size in 1usize..10, extra in 1usize..5) {
+        // A row clue longer than the board can't be placed
+        let mut row_clues = vec![vec![0]; size];
+        row_clues[0] = vec![size + extra];
+        let col_clues = vec![vec![0]; size];
+        prop_assert_eq!(Solver::new(&row_clues, &col_clues).solve(), Solution::None);
+    }
+
+    #[test]
+    fn custom_codes_round_trip(board in board(60)) {
+        let code = PuzzleCode::Custom(board.clone());
+        let parsed: PuzzleCode = code.to_string().parse().unwrap();
+        prop_assert_eq!(parsed.solution(), board.clone());
+        // Including the clues of empty lines
+        prop_assert_eq!(clues(&parsed.solution()), clues(&board));
+    }
+
+    #[test]
+    fn seeded_codes_round_trip(size in 1usize..=255, seed in any::<u64>()) {
+        let code = PuzzleCode::Seeded { size, seed };
+        let parsed: PuzzleCode = code.to_string().parse().unwrap();
+        prop_assert_eq!(parsed, code);
+    }
+
+    // Whatever the player does, playing the replay back on a new board,
+    // after writing it out as text, leaves the same marks
+    #[test]
+    fn replays_reproduce_the_marks(
+        (solution, moves) in board(8).prop_flat_map(|board| {
+            let size = board.size();
+            (Just(board), vec((0u8..9, 0..size, 0..size), 0..60))
+        })
+    ) {
+        let mut board = Board::new(solution.clone());
+        let mut replay = Replay::new(PuzzleCode::Custom(solution.clone()));
+        for (index, (op, row, col)) in moves.into_iter().enumerate() {
+            match op {
+                0 | 1 => board.toggle_mark(row, col, CellType::Filled),
+                2 => board.toggle_mark(row, col, CellType::Cross),
+                3 => board.toggle_mark(row, col, CellType::Dot),
+                4 => board.undo(),
+                5 => board.redo(),
+                6 => board.tentative = !board.tentative,
+                7 => board.commit_tentative(),
+                _ => board.discard_tentative(),
+            }
+            let time = Duration::from_millis(index as u64 * 250);
+            replay.record(time, board.take_actions());
+        }
+
+        let read = Replay::from_text(&replay.to_text()).unwrap();
+        prop_assert_eq!(&read, &replay);
+        let mut replayed = Board::new(read.code.solution());
+        for step in &read.steps {
+            replayed.apply(step.action);
+        }
+        for row in 0..solution.size() {
+            for col in 0..solution.size() {
+                prop_assert_eq!(replayed.mark_at(row, col), board.mark_at(row, col));
+            }
+        }
+        prop_assert_eq!(replayed.is_solved(), board.is_solved());
+    }
+}
+
+#[test]
+fn empty_board_round_trips_with_zero_clues() {
+    let board = BitBoard::new(7);
+    let parsed: PuzzleCode = PuzzleCode::Custom(board.clone())
+        .to_string()
+        .parse()
+        .unwrap();
+    let (row_clues, col_clues) = clues(&parsed.solution());
+    assert!(row_clues.iter().chain(&col_clues).all(|clue| clue == &[0]));
+    assert_eq!(
+        Solver::new(&row_clues, &col_clues).solve(),
+        Solution::Unique(board)
+    );
+}
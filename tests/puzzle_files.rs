@@ -0,0 +1,90 @@
+// Examples of loading puzzle files and of what is reported when they can't
+// be played
+
+use rust_nonogram::board::clues;
+use rust_nonogram::diagnostics::ClueProblem;
+use rust_nonogram::model::BoardLine;
+use rust_nonogram::{PuzzleFile, PuzzleFileError};
+
+#[test]
+fn example_puzzle_files_are_valid() {
+    for name in ["heart.json", "arrow.ron"] {
+        let path = std::path::Path::new("assets/puzzles").join(name);
+        let puzzle = PuzzleFile::load(&path).unwrap();
+        let (rows, columns) = clues(&puzzle.solution);
+        assert_eq!((rows, columns), (puzzle.file.rows, puzzle.file.columns));
+    }
+}
+
+#[test]
+fn invalid_puzzle_files_say_what_is_wrong() {
+    let check = |text: &str| PuzzleFile::from_json(text).unwrap().validate().unwrap_err();
+    let clue_problems = |text: &str| match check(text) {
+        PuzzleFileError::Clues(problems) => problems,
+        err => panic!("expected clue problems, not {:?}", err),
+    };
+    // Every problem with the clues' shape is reported at once
+    let text = r#"{"title": "", "size": 3, "rows": [[1], [2, 2], [0, 1]], "columns": [[1], [1]]}"#;
+    assert_eq!(
+        clue_problems(text),
+        [
+            ClueProblem::ColumnCount { found: 2, size: 3 },
+            ClueProblem::ClueTooLong {
+                line: BoardLine::Row(1),
+                needs: 5,
+                size: 3
+            },
+            ClueProblem::ZeroInClue(BoardLine::Row(2)),
+            ClueProblem::TotalsDiffer {
+                rows: 6,
+                columns: 2
+            },
+        ]
+    );
+    assert_eq!(
+        check(text).problems()[1],
+        "row 2's clue needs 5 cells, but lines are 3 long"
+    );
+    // Each clue fits, but with the last two rows empty column 2 has no room
+    // for its run of 2
+    let text = r#"{"title": "", "size": 3, "rows": [[3], [], []], "columns": [[1], [2], []]}"#;
+    let problems = clue_problems(text);
+    assert_eq!(problems, [ClueProblem::ImpossibleLine(BoardLine::Col(1))]);
+    assert_eq!(
+        problems[0].to_string(),
+        "column 2 can't be filled in to agree with the lines crossing it"
+    );
+    let text = r#"{"title": "", "size": 2, "rows": [[1], [1]], "columns": [[1], [1]]}"#;
+    assert_eq!(clue_problems(text), [ClueProblem::MultipleSolutions]);
+
+    // Every line the solution gets wrong is listed
+    let err = check(
+        r##"{"title": "", "size": 2, "rows": [[1], [1]], "columns": [[2], []], "solution": ["#.", ".#"]}"##,
+    );
+    assert_eq!(
+        err.problems(),
+        [
+            "the solution doesn't match column 1's clue",
+            "the solution doesn't match column 2's clue"
+        ]
+    );
+    assert!(matches!(
+        err,
+        PuzzleFileError::SolutionMismatch(lines)
+            if lines == [BoardLine::Col(0), BoardLine::Col(1)]
+    ));
+    assert!(matches!(
+        check(
+            r##"{"title": "", "size": 1, "rows": [[1]], "columns": [[1]], "solution": ["r"], "palette": {"b": "#0000ff"}}"##
+        ),
+        PuzzleFileError::UnknownKey {
+            row: 0,
+            col: 0,
+            key: 'r'
+        }
+    ));
+    assert!(PuzzleFile::from_json(
+        r#"{"title": "", "size": 1, "rows": [[0]], "columns": [[0]], "colour": 1}"#
+    )
+    .is_err());
+}
@@ -0,0 +1,25 @@
+// Examples of what is reported for replay files that can't be read
+
+use rust_nonogram::replay::Replay;
+
+#[test]
+fn invalid_replays_say_where() {
+    let error = |text: &str| Replay::from_text(text).unwrap_err().to_string();
+    assert_eq!(error("10-5eed\n"), "not a replay file");
+    assert_eq!(
+        error("nonogram-replay 2\ncode 10-5eed\n"),
+        "replay version 2 is not supported"
+    );
+    assert_eq!(
+        error("nonogram-replay 1\n10 f 0,0\n"),
+        "line 2 should be the puzzle code"
+    );
+    assert_eq!(
+        error("nonogram-replay 1\ncode 5-5eed\n10 f 0,0\n\n20 fill 1,1\n"),
+        "line 5: `20 fill 1,1` is not a step"
+    );
+    assert_eq!(
+        error("nonogram-replay 1\ncode 5-5eed\n10 X 2,5\n"),
+        "line 3: row 3, column 6 is outside the board"
+    );
+}
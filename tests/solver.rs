@@ -0,0 +1,20 @@
+// Examples of solving clues
+
+use rust_nonogram::solver::{Solution, Solver};
+
+// Clues for a board that isn't square are refused rather than read past the
+// end of the shorter side
+#[test]
+fn clues_of_different_lengths_are_refused() {
+    let row_clues = vec![vec![1]; 3];
+    let col_clues = vec![vec![1, 1], vec![0]];
+    assert_eq!(
+        Solver::new(&row_clues, &col_clues).find_contradiction(),
+        None
+    );
+    assert_eq!(Solver::new(&row_clues, &col_clues).solve(), Solution::None);
+    assert_eq!(
+        Solver::new(&col_clues, &row_clues).find_contradiction(),
+        None
+    );
+}
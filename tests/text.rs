@@ -0,0 +1,24 @@
+// Examples of boards drawn as plain text
+
+use rust_nonogram::board::BitBoard;
+use rust_nonogram::text::{self, Charset};
+
+#[test]
+fn solutions_render_as_text() {
+    let rows = ["##", ".#", "###.#", "", "#.#.#", "#"];
+    let board = BitBoard::from_fn(6, |row, col| rows[row].as_bytes().get(col) == Some(&b'#'));
+    let expected = "        1
+        1   1   1
+        2 3 1 0 1  0
+      +----------+--+
+    2 |#### . . .| .|
+    1 | .## . . .| .|
+  3 1 |###### .##| .|
+    0 | . . . . .| .|
+1 1 1 |## .## .##| .|
+      +----------+--+
+    1 |## . . . .| .|
+      +----------+--+
+";
+    assert_eq!(text::render_solution(&board, Charset::Ascii), expected);
+}
@@ -0,0 +1,21 @@
+// Examples of the built-in themes
+
+use rust_nonogram::Theme;
+
+#[test]
+fn built_in_themes_round_trip() {
+    for name in Theme::NAMES {
+        let theme = Theme::named(name).unwrap();
+        let text = toml::to_string(&theme).unwrap();
+        assert_eq!(Theme::from_toml(&text).unwrap(), theme, "{}", name);
+    }
+    assert!(Theme::from_toml("background = \"#123\"").is_err());
+}
+
+#[test]
+fn accessible_themes_meet_contrast_minimums() {
+    for name in Theme::ACCESSIBLE {
+        let problems = Theme::named(name).unwrap().contrast_problems();
+        assert!(problems.is_empty(), "{}: {:?}", name, problems);
+    }
+}
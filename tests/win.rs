@@ -0,0 +1,53 @@
+// Examples of the pictures shown when a board is revealed
+
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use rust_nonogram::RevealPicture;
+
+const QUADRANTS: [[u8; 4]; 4] = [
+    [255, 0, 0, 255],
+    [0, 255, 0, 255],
+    [0, 0, 255, 255],
+    [255, 255, 255, 128],
+];
+
+// A 4×4 image with a different color in each 2×2 corner, row by row
+fn quadrant_image(format: TextureFormat) -> Image {
+    let mut data = Vec::new();
+    for y in 0..4 {
+        for x in 0..4 {
+            data.extend(QUADRANTS[y / 2 * 2 + x / 2]);
+        }
+    }
+    let size = Extent3d {
+        width: 4,
+        height: 4,
+        depth_or_array_layers: 1,
+    };
+    Image::new(size, TextureDimension::D2, data, format)
+}
+
+#[test]
+fn images_are_scaled_to_the_board() {
+    let image = quadrant_image(TextureFormat::Rgba8UnormSrgb);
+    let picture = RevealPicture::from_image(&image, 2).unwrap();
+    assert_eq!(picture.size(), 2);
+    for (i, [r, g, b, a]) in QUADRANTS.into_iter().enumerate() {
+        assert_eq!(picture.color(i / 2, i % 2), Color::rgba_u8(r, g, b, a));
+    }
+}
+
+#[test]
+fn linear_images_keep_their_colors() {
+    let image = quadrant_image(TextureFormat::Rgba8Unorm);
+    let picture = RevealPicture::from_image(&image, 2).unwrap();
+    let [r, g, b, a] = QUADRANTS[3].map(|c| c as f32 / 255.);
+    assert_eq!(picture.color(1, 1), Color::rgba_linear(r, g, b, a));
+}
+
+#[test]
+fn images_missing_data_are_refused() {
+    let mut image = quadrant_image(TextureFormat::Rgba8UnormSrgb);
+    image.data.truncate(4 * 4 * 4 - 1);
+    assert_eq!(RevealPicture::from_image(&image, 2), None);
+}